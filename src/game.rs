@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::stellar_core::{camera, ship, solar_system};
+use crate::stellar_core::{camera, clock, floating_origin, ship, solar_system};
 use crate::ui;
 
 pub struct GamePlugin;
@@ -9,6 +9,8 @@ impl Plugin for GamePlugin {
         app
             .add_plugins((
                     camera::CameraPlugin,
+                    clock::ClockPlugin,
+                    floating_origin::FloatingOriginPlugin,
                     ship::ShipPlugin,
                     solar_system::SolarSystemPlugin,
                     ui::info_ui::UIPlugin,
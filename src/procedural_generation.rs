@@ -1,4 +1,4 @@
 pub mod gen_icon;
 pub mod gen_planet;
 pub mod gen_star;
-//pub mod gen_system;
\ No newline at end of file
+pub mod gen_system;
\ No newline at end of file
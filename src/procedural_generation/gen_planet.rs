@@ -14,14 +14,13 @@
 
 use crate::stellar_core::solar_system::Orbit;
 use crate::stellar_core::solar_system::Planet;
+use crate::stellar_utils::unit_conversion::{G, STEFAN_BOLTZMANN, SOL_LUMINOSITY};
 
 use rand_distr::{Distribution, Normal};
 
 pub const EARTH_RADIUS: f64 = 6.371e6;
 pub const EARTH_MASS: f64 = 5.972e24;
 pub const EARTH_GRAVITY: f64 = 9.7803267715;
-pub const STEFAN_BOLTZMANN: f64 = 5.670374419e-8;
-pub const G: f64 = 6.6743015e-11;
 
 
 pub fn generate_planet(earth_mass: f64, density: f64, solar_flux: f64, magnetic_field: f64,
@@ -64,7 +63,8 @@ pub fn generate_planet(earth_mass: f64, density: f64, solar_flux: f64, magnetic_
 
     //albedo: depends on clouds, surface type, etc.
     let albedo = (0.1 + 0.2 * (1.0 - magnetic_field).clamp(0.0, 1.0)) * (1.0 - 0.2 * atmos_pressure.clamp(0.0, 5.0));
-    let _equilibrium_temp = ((solar_flux * (1.0 - albedo)) / (4.0 * STEFAN_BOLTZMANN)).powf(0.25);
+    //solar flux is relative to earth's, so 278.6K is the zero-albedo equilibrium temperature at 1 AU
+    let equilibrium_temp = 278.6 * (solar_flux * (1.0 - albedo)).powf(0.25);
 
     //solar day length: just increase with size
     //let solar_day_length = 24.0 * (radius).sqrt();
@@ -119,6 +119,8 @@ pub fn generate_planet(earth_mass: f64, density: f64, solar_flux: f64, magnetic_
         surface_gravity: surface_gravity, 
         atmos_pressure: atmos_pressure, 
         surface_temperature: temp, 
+        equilibrium_temperature: equilibrium_temp,
+        albedo: albedo,
         atmosphere_composition: vec![(composition.to_string(), 1.0)], 
         magnetic_field_strength: magnetic_field, 
         tectonic_activity: tectonic_activity.1.to_string(), 
@@ -128,6 +130,12 @@ pub fn generate_planet(earth_mass: f64, density: f64, solar_flux: f64, magnetic_
     p
 }
 
+///Radiative equilibrium temperature in kelvin of a body with this albedo, at distance (meters)
+///from a star of this luminosity (solar units).
+pub fn equilibrium_temperature(luminosity: f64, distance: f64, albedo: f64) -> f64 {
+    ((luminosity * SOL_LUMINOSITY * (1.0 - albedo)) / (16.0 * core::f64::consts::PI * STEFAN_BOLTZMANN * distance.powi(2))).powf(0.25)
+}
+
 fn normalize(value: f64, min: f64, max: f64) -> f64 {
     //clamp to 0–1
     let v = (value - min).max(0.0).min(max - min);
@@ -141,6 +149,8 @@ pub struct PlanetData {
     pub surface_gravity: f64,
     pub atmos_pressure: f64,
     pub surface_temperature: f64,
+    pub equilibrium_temperature: f64,
+    pub albedo: f64,
     pub atmosphere_composition: Vec<(String, f64)>,
     pub magnetic_field_strength: f64,
     pub tectonic_activity: String,
@@ -0,0 +1,412 @@
+//Continuous stellar evolution tracks.
+//
+//A track is parameterised by initial mass (solar masses) and metallicity (solar units),
+//and can be queried at any age (in GY) from the collapse of the protostar onwards.
+//Pre-main-sequence and main-sequence stages are analytic; everything after the terminal
+//age main sequence (TAMS) is interpolated between anchor points in log L / log T space,
+//so the star moves smoothly across the HR diagram instead of jumping between snapshots.
+//Mass lost to winds is interpolated the same way, down to the remnant mass by the end.
+
+use super::remnant::{Remnant, initial_final_mass};
+
+///Effective temperature of the sun in kelvin.
+pub const SOL_TEMPERATURE: f64 = 5772.0;
+
+///Evolutionary phase of a star along its track.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    PreMainSequence,
+    MainSequence,
+    Subgiant,
+    RedGiant,
+    HorizontalBranch,
+    AsymptoticGiant,
+    Supergiant,
+    Remnant,
+}
+
+///Snapshot of a star at a particular point on its track. Solar units, temperature in kelvin.
+#[derive(Clone, Debug)]
+pub struct StellarState {
+    pub phase: Phase,
+    pub mass: f64,
+    pub radius: f64,
+    pub luminosity: f64,
+    pub temperature: f64,
+    pub spectral_type: String,
+    ///Magnetic activity (flares, X-rays, winds) relative to the present day sun.
+    pub activity: f64,
+    pub remnant: Option<Remnant>,
+}
+
+///Post-main-sequence anchor point. `duration` is the time spent reaching it from the
+///previous anchor, as a fraction of the main-sequence lifetime. `mass` is what is left
+///of the star once winds have blown the rest away.
+struct Anchor {
+    duration: f64,
+    luminosity: f64,
+    temperature: f64,
+    mass: f64,
+    phase: Phase,
+}
+
+#[derive(Clone, Debug)]
+pub struct EvolutionTrack {
+    pub mass: f64,
+    pub metallicity: f64,
+}
+
+impl EvolutionTrack {
+    pub fn new(mass: f64, metallicity: f64) -> Self {
+        EvolutionTrack { mass, metallicity }
+    }
+
+    ///Time spent burning hydrogen in the core, in GY. Metal-rich stars are dimmer and live longer.
+    pub fn main_sequence_lifetime(&self) -> f64 {
+        let lifespan_modifier = 1.0 + (self.metallicity - 1.0) * 0.1;
+        10.0 / self.mass.powf(2.5) * lifespan_modifier
+    }
+
+    ///Kelvin-Helmholtz contraction time onto the main sequence, in GY.
+    pub fn pre_main_sequence_lifetime(&self) -> f64 {
+        //the smallest red dwarfs take hundreds of millions of years to settle down
+        let exponent = if self.mass < 1.0 { 1.5 } else { 2.5 };
+        (0.04 / self.mass.powf(exponent)).max(1e-4)
+    }
+
+    ///Total time until the star leaves a remnant, in GY.
+    pub fn lifespan(&self) -> f64 {
+        let post_ms: f64 = self.post_main_sequence().iter().map(|a| a.duration).sum();
+        self.pre_main_sequence_lifetime() + self.main_sequence_lifetime() * (1.0 + post_ms)
+    }
+
+    ///Luminosity on the zero age main sequence.
+    pub fn zams_luminosity(&self) -> f64 {
+        let luminosity_modifier = 1.0 - (self.metallicity - 1.0) * 0.1;
+        mass_luminosity(self.mass) * 0.72 * luminosity_modifier
+    }
+
+    ///Radius on the zero age main sequence.
+    pub fn zams_radius(&self) -> f64 {
+        0.89 * self.mass.powf(0.8)
+    }
+
+    pub fn phase_at(&self, age_gy: f64) -> Phase {
+        self.state_at(age_gy).phase
+    }
+
+    ///Returns the state of the star at this age (GY since collapse).
+    pub fn state_at(&self, age_gy: f64) -> StellarState {
+        let age_gy = age_gy.max(0.0);
+        let t_pms = self.pre_main_sequence_lifetime();
+        let t_ms = self.main_sequence_lifetime();
+
+        let (phase, luminosity, temperature, mass) = if age_gy < t_pms {
+            self.pre_main_sequence(age_gy / t_pms)
+        }
+        else if age_gy < t_pms + t_ms {
+            self.main_sequence((age_gy - t_pms) / t_ms)
+        }
+        else if age_gy < self.lifespan() {
+            self.post_main_sequence_at((age_gy - t_pms - t_ms) / t_ms)
+        }
+        else {
+            let remnant = Remnant::new(self.mass, age_gy - self.lifespan(), self.metallicity);
+
+            return StellarState {
+                phase: Phase::Remnant,
+                mass: remnant.mass,
+                radius: remnant.radius,
+                luminosity: remnant.luminosity,
+                temperature: remnant.temperature,
+                spectral_type: remnant.spectral_type(),
+                activity: 0.0,
+                remnant: Some(remnant),
+            };
+        };
+
+        StellarState {
+            phase,
+            mass,
+            radius: radius_from(luminosity, temperature),
+            luminosity,
+            temperature,
+            spectral_type: self.spectral_type(phase, temperature),
+            activity: self.activity(phase, age_gy),
+            remnant: None,
+        }
+    }
+
+    //contraction down the Hayashi track, then across the Henyey track onto the ZAMS.
+    //x is the fraction of the pre-main-sequence lifetime elapsed.
+    fn pre_main_sequence(&self, x: f64) -> (Phase, f64, f64, f64) {
+        let zams_temperature = temperature_from(self.zams_luminosity(), self.zams_radius());
+        let hayashi_temperature = zams_temperature.min(4000.0);
+
+        let radius = self.zams_radius() * (1.0 + 4.0 * (1.0 - x).powi(2));
+        let temperature = hayashi_temperature + (zams_temperature - hayashi_temperature) * x.powi(3);
+
+        (Phase::PreMainSequence, luminosity_from(radius, temperature), temperature, self.mass)
+    }
+
+    //slow brightening and swelling as hydrogen is depleted in the core.
+    //x is the fraction of the main-sequence lifetime elapsed.
+    fn main_sequence(&self, x: f64) -> (Phase, f64, f64, f64) {
+        let luminosity = self.zams_luminosity() * (1.0 + 0.5 * x + 1.7 * x * x);
+        let radius = self.zams_radius() * (1.0 + 0.25 * x + 0.55 * x * x);
+
+        (Phase::MainSequence, luminosity, temperature_from(luminosity, radius), self.mass)
+    }
+
+    //anchor points after the TAMS. Low and intermediate mass stars climb the giant branch,
+    //settle on the horizontal branch and finish on the AGB. Massive stars become supergiants.
+    fn post_main_sequence(&self) -> Vec<Anchor> {
+        let m = self.mass;
+        let (tams_luminosity, tams_temperature) = self.tams();
+        let (_, remnant_mass) = initial_final_mass(m, self.metallicity);
+
+        if m >= 8.0 {
+            //the most massive stars never make it over to the red side. their winds strip
+            //a fifth of the star before it collapses, and the hottest lose half
+            let supergiant_temperature = if m > 40.0 { 20000.0 } else { 3500.0 };
+            let wind = if m > 40.0 { 0.5 } else { 0.2 };
+            let collapse_mass = (m * (1.0 - wind)).max(remnant_mass);
+
+            return vec![
+                Anchor {
+                    duration: 0.01,
+                    luminosity: tams_luminosity * 1.2,
+                    temperature: (supergiant_temperature + tams_temperature) / 2.0,
+                    mass: m - (m - collapse_mass) * 0.1,
+                    phase: Phase::Subgiant
+                },
+                Anchor {
+                    duration: 0.1,
+                    luminosity: tams_luminosity * 1.5,
+                    temperature: supergiant_temperature,
+                    mass: collapse_mass,
+                    phase: Phase::Supergiant
+                },
+            ];
+        }
+
+        //low mass stars have degenerate cores and ignite helium in a flash at the RGB tip
+        let degenerate = m < 2.0;
+        let rgb_tip_luminosity = if degenerate { 2500.0 } else { 300.0 * m * m };
+        let hb_luminosity = if degenerate { 50.0 } else { rgb_tip_luminosity * 0.4 };
+        let hb_temperature = (4800.0 + 600.0 * (m - 1.0).max(0.0)).min(12000.0);
+        //the loose envelope up the giant branch blows off in winds, and the AGB superwind
+        //takes the rest, leaving the bare core as the white dwarf
+        let rgb_tip_mass = remnant_mass + (m - remnant_mass) * if degenerate { 0.6 } else { 0.95 };
+
+        vec![
+            Anchor {
+                duration: if degenerate { 0.1 } else { 0.02 },
+                luminosity: tams_luminosity * 1.1,
+                temperature: (tams_temperature * 0.85).min(5000.0),
+                mass: m,
+                phase: Phase::Subgiant
+            },
+            Anchor {
+                duration: 0.05,
+                luminosity: rgb_tip_luminosity,
+                temperature: 3200.0 + 150.0 * m,
+                mass: rgb_tip_mass,
+                phase: Phase::RedGiant
+            },
+            Anchor {
+                duration: 0.001,
+                luminosity: hb_luminosity,
+                temperature: hb_temperature,
+                mass: rgb_tip_mass,
+                phase: Phase::RedGiant
+            },
+            Anchor {
+                duration: 0.1,
+                luminosity: hb_luminosity * 1.5,
+                temperature: hb_temperature * 0.9,
+                mass: rgb_tip_mass,
+                phase: Phase::HorizontalBranch
+            },
+            Anchor {
+                duration: 0.01,
+                luminosity: 3000.0 * m.max(1.0).powf(1.2),
+                temperature: 3000.0,
+                mass: remnant_mass,
+                phase: Phase::AsymptoticGiant
+            },
+        ]
+    }
+
+    //x is the time since the TAMS as a fraction of the main-sequence lifetime.
+    fn post_main_sequence_at(&self, x: f64) -> (Phase, f64, f64, f64) {
+        let (mut luminosity, mut temperature) = self.tams();
+        let mut mass = self.mass;
+        let mut elapsed = 0.0;
+
+        for anchor in self.post_main_sequence() {
+            if x < elapsed + anchor.duration {
+                //interpolate in log space so the star moves smoothly across the HR diagram.
+                //winds blow at a steady rate through each stage
+                let t = (x - elapsed) / anchor.duration;
+                let luminosity = log_lerp(luminosity, anchor.luminosity, t);
+                let temperature = log_lerp(temperature, anchor.temperature, t);
+                let mass = mass + (anchor.mass - mass) * t;
+                return (anchor.phase, luminosity, temperature, mass);
+            }
+
+            elapsed += anchor.duration;
+            luminosity = anchor.luminosity;
+            temperature = anchor.temperature;
+            mass = anchor.mass;
+        }
+
+        (Phase::Remnant, luminosity, temperature, mass)
+    }
+
+    //luminosity and temperature at the end of the main sequence
+    fn tams(&self) -> (f64, f64) {
+        let (_, luminosity, temperature, _) = self.main_sequence(1.0);
+        (luminosity, temperature)
+    }
+
+    //young stars spin fast and are saturated. activity then decays as they spin down (Skumanich),
+    //and giants have spun down for good.
+    fn activity(&self, phase: Phase, age_gy: f64) -> f64 {
+        match phase {
+            Phase::PreMainSequence => 1000.0,
+            Phase::MainSequence => (age_gy / 4.6).powf(-1.5).clamp(0.05, 1000.0),
+            Phase::Remnant => 0.0,
+            _ => 0.1,
+        }
+    }
+
+    fn spectral_type(&self, phase: Phase, temperature: f64) -> String {
+        match phase {
+            //Herbig Ae/Be stars are the intermediate mass siblings of T Tauri stars
+            Phase::PreMainSequence if self.mass >= 2.0 => "HAeBe".to_string(),
+            Phase::PreMainSequence => "TT".to_string(),
+            _ => spectral_class(temperature).to_string(),
+        }
+    }
+}
+
+///Piecewise main-sequence mass-luminosity relation, in solar units.
+pub fn mass_luminosity(mass: f64) -> f64 {
+    match mass {
+        x if (0.0..0.43).contains(&x) => 0.23 * mass.powf(2.3),
+        x if (0.43..2.0).contains(&x) => mass.powf(4.0),
+        x if (2.0..20.0).contains(&x) => 1.5 * mass.powf(3.5),
+        x if x >= 20.0 => 3200.0 * mass,
+        _ => 0.0
+    }
+}
+
+///Harvard spectral class from effective temperature. Applies to every phase, giants included.
+pub fn spectral_class(temperature: f64) -> char {
+    match temperature {
+        x if x >= 30000.0 => 'O',
+        x if x >= 10000.0 => 'B',
+        x if x >= 7500.0 => 'A',
+        x if x >= 6000.0 => 'F',
+        x if x >= 5200.0 => 'G',
+        x if x >= 3700.0 => 'K',
+        x if x >= 2100.0 => 'M',
+        x if x >= 1300.0 => 'L',
+        x if x >= 600.0 => 'T',
+        x if x >= 0.0 => 'Y',
+        _ => '.'
+    }
+}
+
+///Stefan-Boltzmann in solar units: radius from luminosity and temperature.
+pub fn radius_from(luminosity: f64, temperature: f64) -> f64 {
+    luminosity.sqrt() / (temperature / SOL_TEMPERATURE).powi(2)
+}
+
+///Stefan-Boltzmann in solar units: temperature from luminosity and radius.
+pub fn temperature_from(luminosity: f64, radius: f64) -> f64 {
+    (luminosity / radius.powi(2)).powf(0.25) * SOL_TEMPERATURE
+}
+
+///Stefan-Boltzmann in solar units: luminosity from radius and temperature.
+pub fn luminosity_from(radius: f64, temperature: f64) -> f64 {
+    radius.powi(2) * (temperature / SOL_TEMPERATURE).powi(4)
+}
+
+fn log_lerp(start: f64, end: f64, t: f64) -> f64 {
+    (start.ln() + (end.ln() - start.ln()) * t).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //stars on every kind of track: red dwarf, sun-like, intermediate mass, supergiants red and blue
+    const MASSES: [f64; 5] = [0.5, 1.0, 3.0, 15.0, 60.0];
+
+    //ages from collapse to well past death, packed more densely after the main sequence
+    fn ages(track: &EvolutionTrack) -> Vec<f64> {
+        let tams = track.pre_main_sequence_lifetime() + track.main_sequence_lifetime();
+        let early = (0..200).map(|i| tams * i as f64 / 200.0);
+        let late = (0..2000).map(|i| tams + (track.lifespan() - tams) * 1.1 * i as f64 / 2000.0);
+        early.chain(late).collect()
+    }
+
+    #[test]
+    fn phases_advance_in_order() {
+        for mass in MASSES {
+            let track = EvolutionTrack::new(mass, 1.0);
+            let mut previous = track.state_at(0.0);
+            assert_eq!(previous.phase, Phase::PreMainSequence);
+
+            for age in ages(&track) {
+                let state = track.state_at(age);
+                assert!(state.phase as u8 >= previous.phase as u8, "{mass}: {:?} after {:?} at {age}", state.phase, previous.phase);
+                //winds only ever take mass away
+                assert!(state.mass <= previous.mass + 1e-12, "{mass}: gained mass at {age}");
+                previous = state;
+            }
+            assert_eq!(previous.phase, Phase::Remnant);
+        }
+    }
+
+    #[test]
+    fn sun_today() {
+        let sun = EvolutionTrack::new(1.0, 1.0).state_at(4.6);
+        assert_eq!(sun.phase, Phase::MainSequence);
+        assert!((sun.luminosity - 1.0).abs() < 0.2, "{}", sun.luminosity);
+        assert!((sun.temperature / SOL_TEMPERATURE - 1.0).abs() < 0.02, "{}", sun.temperature);
+        assert_eq!(sun.spectral_type, "G");
+    }
+
+    #[test]
+    fn giants_get_cooler_letters_as_they_cool() {
+        let classes = "OBAFGKMLTY";
+
+        for mass in MASSES {
+            let track = EvolutionTrack::new(mass, 1.0);
+            let giants = ages(&track).into_iter()
+                .map(|age| track.state_at(age))
+                .filter(|state| !matches!(state.phase, Phase::PreMainSequence | Phase::MainSequence | Phase::Remnant));
+
+            let mut previous: Option<StellarState> = None;
+            for state in giants {
+                let class = classes.find(&state.spectral_type).expect("not a spectral class");
+                if let Some(previous) = previous.filter(|previous| state.temperature < previous.temperature) {
+                    let previous_class = classes.find(&previous.spectral_type).unwrap();
+                    assert!(class >= previous_class, "{mass}: {} at {:.0}K after {} at {:.0}K",
+                        state.spectral_type, state.temperature, previous.spectral_type, previous.temperature);
+                }
+                previous = Some(state);
+            }
+        }
+
+        //a red giant is an M star, and the blue supergiants stay hot
+        let sun = EvolutionTrack::new(1.0, 1.0);
+        let tip = sun.pre_main_sequence_lifetime() + sun.main_sequence_lifetime() * 1.15 - 1e-6;
+        assert_eq!(sun.state_at(tip).spectral_type, "M");
+        let blue = EvolutionTrack::new(60.0, 1.0);
+        assert_eq!(blue.state_at(blue.lifespan() - 1e-9).spectral_type, "B");
+    }
+}
@@ -0,0 +1,323 @@
+//Compact remnants: white dwarfs, neutron stars and black holes.
+//
+//Masses are in solar masses and radii in solar radii, to match the rest of gen_star.
+//Black hole and accretion disk geometry is kept in meters, since that is what the physics wants.
+
+use std::f64::consts::PI;
+
+use super::evolution::{temperature_from, luminosity_from};
+use crate::stellar_utils::unit_conversion::*;
+
+pub const CHANDRASEKHAR_MASS: f64 = 1.44;
+///Tolman-Oppenheimer-Volkoff limit: the heaviest neutron star the equation of state can support.
+pub const TOV_MASS: f64 = 2.2;
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RemnantKind {
+    WhiteDwarf,
+    NeutronStar,
+    BlackHole,
+}
+
+#[derive(Clone, Debug)]
+pub struct Remnant {
+    pub kind: RemnantKind,
+    ///In solar masses.
+    pub mass: f64,
+    ///In solar radii. For black holes this is the event horizon.
+    pub radius: f64,
+    ///Surface temperature in kelvin.
+    pub temperature: f64,
+    ///In solar units.
+    pub luminosity: f64,
+    ///GY since the remnant formed.
+    pub cooling_age: f64,
+    pub pulsar: Option<Pulsar>,
+    pub black_hole: Option<BlackHole>,
+}
+
+///Spin state of a neutron star, slowing down through magnetic dipole radiation.
+#[derive(Clone, Debug)]
+pub struct Pulsar {
+    ///Seconds.
+    pub spin_period: f64,
+    ///Seconds per second.
+    pub period_derivative: f64,
+    ///Surface dipole field in gauss.
+    pub magnetic_field: f64,
+    ///Rotational energy loss in solar units.
+    pub spin_down_luminosity: f64,
+    ///False once the pulsar has crossed the death line and stopped emitting.
+    pub active: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct BlackHole {
+    ///In solar masses.
+    pub mass: f64,
+    ///Dimensionless spin parameter a*, from 0 to just below 1.
+    pub spin: f64,
+    ///Outer event horizon in meters.
+    pub event_horizon: f64,
+    ///Prograde photon orbit in meters.
+    pub photon_sphere: f64,
+    ///Innermost stable circular orbit in meters.
+    pub isco: f64,
+    pub disk: AccretionDisk,
+}
+
+///Thin Shakura-Sunyaev disk around a black hole.
+#[derive(Clone, Debug)]
+pub struct AccretionDisk {
+    ///Meters. The disk is truncated at the ISCO.
+    pub inner_radius: f64,
+    ///Meters.
+    pub outer_radius: f64,
+    ///kg/s.
+    pub accretion_rate: f64,
+    ///Fraction of rest mass energy radiated away.
+    pub efficiency: f64,
+    ///In solar units.
+    pub luminosity: f64,
+    ///Kelvin.
+    pub peak_temperature: f64,
+}
+
+impl Remnant {
+    ///Builds the remnant left by a star of this initial mass, cooling_age GY after it died.
+    pub fn new(progenitor_mass: f64, cooling_age: f64, metallicity: f64) -> Self {
+        let (kind, mass) = initial_final_mass(progenitor_mass, metallicity);
+        let cooling_age = cooling_age.max(0.0);
+
+        match kind {
+            RemnantKind::WhiteDwarf => {
+                let radius = white_dwarf_radius(mass);
+                let luminosity = white_dwarf_luminosity(mass, cooling_age);
+
+                Remnant {
+                    kind, mass, radius, luminosity, cooling_age,
+                    temperature: temperature_from(luminosity, radius),
+                    pulsar: None,
+                    black_hole: None,
+                }
+            },
+            RemnantKind::NeutronStar => {
+                let radius = neutron_star_radius(mass);
+                let temperature = neutron_star_temperature(cooling_age);
+
+                Remnant {
+                    kind, mass, radius, temperature, cooling_age,
+                    luminosity: luminosity_from(radius, temperature),
+                    pulsar: Some(Pulsar::new(0.02, 1e12, gigayears(cooling_age))),
+                    black_hole: None,
+                }
+            },
+            RemnantKind::BlackHole => {
+                let black_hole = BlackHole::new(mass, 0.5, 0.001);
+
+                Remnant {
+                    kind, mass, cooling_age,
+                    radius: black_hole.event_horizon / SOL_RADIUS,
+                    temperature: 0.0, //hawking radiation is wholly negligible
+                    luminosity: black_hole.disk.luminosity,
+                    pulsar: None,
+                    black_hole: Some(black_hole),
+                }
+            },
+        }
+    }
+
+    pub fn spectral_type(&self) -> String {
+        match self.kind {
+            RemnantKind::WhiteDwarf => "W",
+            RemnantKind::NeutronStar => "N",
+            RemnantKind::BlackHole => "X",
+        }.to_string()
+    }
+}
+
+impl Pulsar {
+    ///Spins a pulsar born with period p0 (s) and field b (gauss) down for age seconds.
+    pub fn new(p0: f64, b: f64, age: f64) -> Self {
+        //magnetic dipole braking: B = 3.2e19 sqrt(P * Pdot) gauss, so P^2 grows linearly with time
+        let k = 3.2e19_f64.powi(2);
+        let spin_period = (p0.powi(2) + 2.0 * b.powi(2) * age / k).sqrt();
+        let period_derivative = b.powi(2) / (k * spin_period);
+
+        //canonical moment of inertia of 1e38 kg m^2
+        let spin_down_power = 4.0 * PI.powi(2) * 1e38 * period_derivative / spin_period.powi(3);
+
+        Pulsar {
+            spin_period,
+            period_derivative,
+            magnetic_field: b,
+            spin_down_luminosity: spin_down_power / SOL_LUMINOSITY,
+            active: b / spin_period.powi(2) > 0.17e12,
+        }
+    }
+}
+
+impl BlackHole {
+    ///Kerr black hole of this mass (solar masses) and spin, fed at a fraction of its Eddington limit.
+    pub fn new(mass: f64, spin: f64, eddington_ratio: f64) -> Self {
+        let spin = spin.clamp(0.0, 0.998);
+        let r_g = G * mass * SOL_MASS / SPEED_OF_LIGHT.powi(2);
+
+        let event_horizon = r_g * (1.0 + (1.0 - spin * spin).sqrt());
+        let photon_sphere = 2.0 * r_g * (1.0 + ((2.0 / 3.0) * (-spin).acos()).cos());
+
+        //Bardeen, Press & Teukolsky prograde ISCO
+        let z1 = 1.0 + (1.0 - spin * spin).cbrt() * ((1.0 + spin).cbrt() + (1.0 - spin).cbrt());
+        let z2 = (3.0 * spin * spin + z1 * z1).sqrt();
+        let isco = r_g * (3.0 + z2 - ((3.0 - z1) * (3.0 + z1 + 2.0 * z2)).sqrt());
+
+        //binding energy at the ISCO is what the disk can radiate
+        let efficiency = 1.0 - (1.0 - 2.0 * r_g / (3.0 * isco)).sqrt();
+        let eddington_luminosity = 1.26e31 * mass;
+        let luminosity = eddington_ratio * eddington_luminosity;
+        let accretion_rate = luminosity / (efficiency * SPEED_OF_LIGHT.powi(2));
+
+        let mut disk = AccretionDisk {
+            inner_radius: isco,
+            outer_radius: 1000.0 * r_g,
+            accretion_rate,
+            efficiency,
+            luminosity: luminosity / SOL_LUMINOSITY,
+            peak_temperature: 0.0,
+        };
+        //the temperature profile peaks just outside the inner edge
+        disk.peak_temperature = disk.temperature_at(isco * 49.0 / 36.0, mass);
+
+        BlackHole { mass, spin, event_horizon, photon_sphere, isco, disk }
+    }
+}
+
+impl AccretionDisk {
+    ///Effective temperature (K) of the disk at radius r (m) around a black hole of this mass.
+    pub fn temperature_at(&self, r: f64, mass: f64) -> f64 {
+        if r <= self.inner_radius || r > self.outer_radius {
+            return 0.0;
+        }
+
+        let flux = 3.0 * G * mass * SOL_MASS * self.accretion_rate / (8.0 * PI * r.powi(3))
+            * (1.0 - (self.inner_radius / r).sqrt());
+
+        (flux / STEFAN_BOLTZMANN).powf(0.25)
+    }
+}
+
+///Initial-to-final mass relation. Returns the kind of remnant and its mass in solar masses.
+pub fn initial_final_mass(initial: f64, metallicity: f64) -> (RemnantKind, f64) {
+    //Cummings et al. (2018) semi-empirical fit for white dwarfs
+    let final_mass = match initial {
+        x if x < 0.87 => (0.0873 * x + 0.476).min(x),
+        x if x < 2.8 => 0.0873 * x + 0.476,
+        x if x < 3.65 => 0.181 * x + 0.210,
+        x if x < 8.2 => 0.0835 * x + 0.565,
+        //core collapse. fallback onto the proto-neutron star grows with progenitor mass
+        x if x < 25.0 => 1.2 + 0.06 * (x - 8.2),
+        //metal-rich winds strip more of the envelope before collapse
+        x => (0.25 * x + 1.0) * (1.0 - 0.2 * (metallicity - 1.0)).clamp(0.5, 1.5),
+    };
+
+    let kind = match final_mass {
+        m if initial < 8.2 && m < CHANDRASEKHAR_MASS => RemnantKind::WhiteDwarf,
+        m if m <= TOV_MASS => RemnantKind::NeutronStar,
+        _ => RemnantKind::BlackHole,
+    };
+
+    let final_mass = match kind {
+        RemnantKind::BlackHole => final_mass.max(3.0),
+        _ => final_mass,
+    };
+
+    (kind, final_mass)
+}
+
+///Nauenberg's fit to the Chandrasekhar mass-radius relation, in solar radii.
+///Heavier white dwarfs are smaller, collapsing to zero at the Chandrasekhar limit.
+pub fn white_dwarf_radius(mass: f64) -> f64 {
+    let x = (mass / CHANDRASEKHAR_MASS).clamp(1e-3, 0.999);
+    0.0114 * (x.powf(-2.0 / 3.0) - x.powf(2.0 / 3.0)).sqrt()
+}
+
+///Mestel cooling law for a carbon white dwarf, in solar units.
+pub fn white_dwarf_luminosity(mass: f64, cooling_age: f64) -> f64 {
+    //a freshly formed dwarf is still a planetary nebula nucleus, so start the clock at 100kyr
+    let t = (cooling_age * 1e9).max(1e5);
+    (t /(8.8e6 * mass.powf(5.0 / 7.0))).powf(-7.0 / 5.0)
+}
+
+///Neutron star radius in solar radii. Approximates a stiff equation of state as an
+///n=1.5 polytrope, so radius shrinks as mass grows: about 12km at 1.4 solar masses.
+pub fn neutron_star_radius(mass: f64) -> f64 {
+    let radius_km = 12.0 * (mass / 1.4).powf(-1.0 / 3.0);
+    radius_km * 1000.0 / SOL_RADIUS
+}
+
+///Surface temperature (K) of a neutron star. Neutrino cooling keeps it near a million kelvin
+///for the first 100,000 years, after which photon cooling takes over.
+pub fn neutron_star_temperature(cooling_age: f64) -> f64 {
+    let t = (cooling_age * 1e9).max(1.0);
+    match t {
+        x if x < 1e5 => 1e6 * (x / 1e5).powf(-1.0 / 12.0),
+        x => 1e6 * (x / 1e5).powf(-0.5),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64, tolerance: f64) {
+        assert!((a / b - 1.0).abs() < tolerance, "{a} vs {b}");
+    }
+
+    #[test]
+    fn initial_final_mass_branches() {
+        let (kind, mass) = initial_final_mass(1.0, 1.0);
+        assert_eq!(kind, RemnantKind::WhiteDwarf);
+        assert_close(mass, 0.56, 0.01);
+
+        //the white dwarf fits meet at their boundaries
+        for boundary in [2.8, 3.65] {
+            let (_, below) = initial_final_mass(boundary - 1e-9, 1.0);
+            let (_, above) = initial_final_mass(boundary, 1.0);
+            assert_close(below, above, 0.01);
+        }
+
+        //the heaviest white dwarf progenitors, then core collapse
+        assert_eq!(initial_final_mass(8.19, 1.0).0, RemnantKind::WhiteDwarf);
+        let (kind, mass) = initial_final_mass(8.2, 1.0);
+        assert_eq!(kind, RemnantKind::NeutronStar);
+        assert!(mass < TOV_MASS, "{mass}");
+
+        let (kind, mass) = initial_final_mass(40.0, 1.0);
+        assert_eq!(kind, RemnantKind::BlackHole);
+        assert!(mass >= 3.0);
+    }
+
+    #[test]
+    fn white_dwarfs_shrink_as_they_get_heavier() {
+        assert_close(white_dwarf_radius(0.6), 0.012, 0.1);
+
+        let radii: Vec<f64> = [0.2, 0.4, 0.6, 0.8, 1.0, 1.2, 1.4].into_iter().map(white_dwarf_radius).collect();
+        assert!(radii.windows(2).all(|pair| pair[1] < pair[0]), "{radii:?}");
+    }
+
+    #[test]
+    fn schwarzschild_geometry() {
+        let black_hole = BlackHole::new(10.0, 0.0, 0.001);
+        let r_g = G * 10.0 * SOL_MASS / SPEED_OF_LIGHT.powi(2);
+
+        assert_close(black_hole.event_horizon, 2.0 * r_g, 1e-12);
+        assert_close(black_hole.photon_sphere, 3.0 * r_g, 1e-12);
+        assert_close(black_hole.isco, 6.0 * r_g, 1e-12);
+
+        //spinning brings the horizon and the disk in closer
+        let spinning = BlackHole::new(10.0, 0.9, 0.001);
+        assert!(spinning.event_horizon < black_hole.event_horizon);
+        assert!(spinning.isco < black_hole.isco);
+    }
+}
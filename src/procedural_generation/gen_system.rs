@@ -1,6 +1,6 @@
 use rand::{rngs::SmallRng, SeedableRng, Rng};
 use sha2::{Sha256, Digest};
-use std::f64::consts::E;
+use std::f64::consts::{E, TAU};
 
 use crate::stellar_core::solar_system::{
     Star,
@@ -12,9 +12,46 @@ use crate::stellar_core::solar_system::{
 use crate::stellar_utils::unit_conversion::*;
 
 use crate::stellar_core::solar_system::Orbit;
+use crate::stellar_core::solar_system::celestial_body::star::disk::DISK_LIFETIME;
+use crate::procedural_generation::gen_star::EvolutionTrack;
 use crate::stellar_utils::MTree;
 
-pub fn gen_system(seed: &str) -> MTree<CelestialBody> {
+use bevy::prelude::Resource;
+
+pub mod stability;
+pub use stability::StabilityReport;
+use stability::hill_radius;
+
+///Knobs for the system generator.
+#[derive(Resource, Clone, Debug)]
+pub struct SystemConfig {
+    ///Metallicity (solar units) given to every star. None rolls it from the seed.
+    pub metallicity: Option<f64>,
+    ///Chance that a planet forming beyond the frost line of a solar metallicity star grows into a giant.
+    pub giant_probability: f64,
+    ///Exponent on metallicity for giant planet occurrence.
+    ///Fischer & Valenti (2005) found occurrence goes as 10^(2[Fe/H]), which is 2.
+    pub giant_metallicity_exponent: f64,
+    ///Nudge, merge or eject bodies until every orbit passes the stability checks.
+    pub repair_orbits: bool,
+    ///Closest two neighbouring bodies may sit, in mutual Hill radii.
+    pub min_hill_spacing: f64,
+}
+
+impl Default for SystemConfig {
+    fn default() -> Self {
+        SystemConfig {
+            metallicity: None,
+            giant_probability: 0.1,
+            giant_metallicity_exponent: 2.0,
+            repair_orbits: true,
+            min_hill_spacing: stability::STABLE_HILL_SPACING,
+        }
+    }
+}
+
+///Generates the system for this seed, along with a report from the stability pass.
+pub fn gen_system(seed: &str, config: &SystemConfig) -> (MTree<CelestialBody>, StabilityReport) {
     //init our rng from the seed
     let mut rng: SmallRng = random_gen_from_string(seed);
 
@@ -31,9 +68,16 @@ pub fn gen_system(seed: &str) -> MTree<CelestialBody> {
     let mut star_vec: Vec<Star> = vec![];
     for _ in 0..star_amount {
         let starmass = imf(&mut rng);
-        let age_gy = rng.random_range(0.0..13.8);
-        let metallicity = rng.random_range(0.0..=1.3);
-        //star_vec.push(Star::new(starmass, age_gy, metallicity));
+        //a uniform draw almost never lands inside the first few million years,
+        //so give young systems that are still forming their planets a fixed share
+        let age_gy = match rng.random_bool(0.05) {
+            true => rng.random_range(0.0..0.01),
+            false => rng.random_range(0.0..13.8),
+        };
+        //[Fe/H] of stars in the solar neighbourhood, converted to solar units
+        let fe_h: f64 = rng.sample(rand_distr::Normal::new(-0.1, 0.25).unwrap());
+        let metallicity = config.metallicity.unwrap_or(10.0_f64.powf(fe_h.clamp(-2.5, 0.5)));
+        star_vec.push(Star::new(starmass, age_gy, metallicity));
     }
 
     let mut system_tree = match star_amount {
@@ -46,10 +90,32 @@ pub fn gen_system(seed: &str) -> MTree<CelestialBody> {
     //this is the root barycenter of the entire system.
     let mut system_root = system_tree.root_handle();
 
-    let starmass = imf(&mut rng);
+    let (starmass, track, disk_progress) = match system_root.value() {
+        //while the birth disk is around, planets have only gathered part of their final mass
+        CelestialBody::Star(star) if star.disk.is_some() => (
+            star.mass,
+            star.track.clone(),
+            Some((star.age / (3.0 * DISK_LIFETIME)).clamp(0.05, 1.0))
+        ),
+        CelestialBody::Star(star) => (star.mass, star.track.clone(), None),
+        _ => {
+            let starmass = imf(&mut rng);
+            (starmass, EvolutionTrack::new(starmass, config.metallicity.unwrap_or(1.0)), None)
+        }
+    };
+    let metallicity = track.metallicity;
+
+    //the planetary system mass pool. dust makes up a fixed share of the metals in the birth disk,
+    //so metal poor stars have less to build planets from, and what they do build is smaller
+    let pmass = sols(starmass) * 0.00001 * metallicity;
+    let rocky_scale = metallicity.sqrt();
+
+    //giant cores only reach runaway gas accretion when there is plenty of solid material
+    let giant_chance = (config.giant_probability * metallicity.powf(config.giant_metallicity_exponent))
+        .clamp(0.0, 0.9);
 
-    //the planetary system mass pool
-    let pmass = sols(starmass) * 0.00001;
+    //the frost line (AU) sits where it did when the planets formed, set by the zero age luminosity
+    let frost_line = 2.7 * track.zams_luminosity().sqrt();
 
     //these parameters are fed into the mass distribution function
     let l_stable= rng.random_range(0.0..20.0);
@@ -68,29 +134,52 @@ pub fn gen_system(seed: &str) -> MTree<CelestialBody> {
         current_distance = current_distance + rng.random_range(0.0..system_size);
 
         //sample the mass from our function and add this value to the running total
-        let planet_mass = earths(
+        let solid_mass = earths(
             planet_mass_function(current_distance, l_stable, z_unstable, 0.232, 4.313)
-        );
-        current_mass += planet_mass;
-
-        let orbit = Orbit::new(
-            current_distance,
-            current_distance * random_eccentricity(&mut rng),
+        ) * rocky_scale;
+        current_mass += solid_mass;
+
+        //a few giants migrate inward after forming, so the hot ones get a small share
+        let giant = match current_distance > frost_line {
+            true => rng.random_bool(giant_chance),
+            false => rng.random_bool(giant_chance * 0.1),
+        };
+
+        //giants are mostly hydrogen and helium captured onto the core, which the solid budget does not pay for
+        let (planet_mass, density) = match giant {
+            true => (earths(10.0 * rng.random_range(3.0_f64..300.0)), rng.random_range(600.0..1800.0)),
+            false => (solid_mass, rng.random_range(800.0..8000.0)),
+        };
+
+        //distances are generated in AU, orbits are in meters
+        let mut orbit = Orbit::new(
+            au(current_distance),
+            au(current_distance) * random_eccentricity(&mut rng),
             rng.random_range(0.0..1.0));
+        orbit.argument_of_periapsis = rng.random_range(0.0..TAU);
 
-        let mut planet_system = MTree::new(
-            CelestialBody::Planet(Planet::new(
+        let mut planet = Planet::new(
             to_earth(planet_mass / 1.0),
-            rng.random_range(800.0..8000.0),
+            density,
             solar_flux_function(current_distance, system_size) * 1.0,
             rng.random_range(0.0..1.0),
             orbit
-        )));
+        );
 
-        let moon_amount = (rng.random_range(0.0..=to_earth(planet_mass)) * 10.0).trunc() as u32;
+        if let Some(progress) = disk_progress {
+            planet = planet.into_protoplanet(progress);
+        }
+
+        let mut planet_system = MTree::new(CelestialBody::Planet(planet));
+
+        //protoplanets have not had time to capture moons yet
+        let moon_amount = match disk_progress {
+            Some(_) => 0,
+            None => (rng.random_range(0.0..=to_earth(solid_mass)) * 10.0).trunc() as u32
+        };
 
         for _ in 0..moon_amount {
-            let moon_mass = planet_mass * rng.random_range(0.001..0.01);
+            let moon_mass = solid_mass * rng.random_range(0.001..0.01);
             current_mass += moon_mass;
 
             let root_val = planet_system.root_handle();
@@ -106,19 +195,21 @@ pub fn gen_system(seed: &str) -> MTree<CelestialBody> {
 
             let max_hill = max_moon_orbit(
                 &mut rng, 
-                root_star.mass, 
+                sols(root_star.mass), 
                 planet_mass,
                 root_planet.orbit.semi_major_axis());
 
             let moon_density = rng.random_range(500.0..8000.0);
+            //planet radius is in km
             let roche_limit = 
-                root_planet.radius * 
+                root_planet.radius * 1000.0 * 
                 (2.0 * (root_planet.density / moon_density)).powf(0.333);
 
-            let moon_orbit = Orbit::new(
+            let mut moon_orbit = Orbit::new(
                 max_hill.max(roche_limit), 
                 max_hill.max(roche_limit) * random_eccentricity(&mut rng), 
                 rng.random_range(0.0..1.0));
+            moon_orbit.argument_of_periapsis = rng.random_range(0.0..TAU);
 
             planet_system.append(0,
                 CelestialBody::Planet(Planet::new(
@@ -133,7 +224,12 @@ pub fn gen_system(seed: &str) -> MTree<CelestialBody> {
         system_root.merge(planet_system);
     }
 
-    system_tree
+    let report = match config.repair_orbits {
+        true => stability::repair_stability(&mut system_tree, config.min_hill_spacing),
+        false => stability::check_stability(&system_tree, config.min_hill_spacing),
+    };
+
+    (system_tree, report)
 }
 
 fn random_gen_from_string<R: SeedableRng<Seed = [u8; 32]>>(s: &str) -> R {
@@ -267,13 +363,31 @@ fn random_eccentricity<R: Rng>(rng: &mut R) -> f64 {
     (x.powi(2) + y.powi(2)).sqrt().clamp(0.0, 0.95)
 }
 
-///Hill radius in AU. This is the Sphere of Influence around an object
-fn hill_radius(star_mass: f64, planet_mass: f64, semi_major: f64) -> f64 {
-    semi_major * (planet_mass / (3.0 * star_mass)).powf(1.0 / 3.0)
-}
-
-///Max safe moon orbit in AU. Up to 50% of the Hill sphere radius/
+///Max safe moon orbit in meters, somewhere up to half of the planet's Hill radius.
 fn max_moon_orbit<R: Rng>(rng: &mut R, star_mass: f64, planet_mass: f64, semi_major: f64) -> f64 {
     let hill = hill_radius(star_mass, planet_mass, semi_major);
     hill * rng.random_range(0.05..0.5)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //giants going round the star across the same seeds, with every star given this metallicity
+    fn giants(metallicity: f64) -> usize {
+        let config = SystemConfig { metallicity: Some(metallicity), ..Default::default() };
+
+        (0..200).map(|i| {
+            let (tree, _) = gen_system(&format!("metallicity {i}"), &config);
+            tree.children(tree.root_id()).into_iter()
+                .filter(|id| matches!(tree.get_value(*id), Some(CelestialBody::Planet(planet)) if planet.final_mass >= 30.0))
+                .count()
+        }).sum()
+    }
+
+    #[test]
+    fn metal_rich_stars_host_more_giants() {
+        let (poor, solar, rich) = (giants(0.3), giants(1.0), giants(3.0));
+        assert!(poor < solar && solar < rich, "{poor} {solar} {rich}");
+        assert!(rich > 4 * poor.max(1), "{poor} {rich}");
+    }
+}
@@ -0,0 +1,414 @@
+//Orbital stability checks and repairs for generated systems.
+//
+//Works on the MTree straight out of gen_system, so orbits are in meters,
+//star masses in solar masses and planet masses in earth masses.
+
+use std::f64::consts::PI;
+
+use bevy::prelude::Resource;
+
+use crate::stellar_core::solar_system::{CelestialBody, Planet};
+use crate::stellar_utils::MTree;
+use crate::stellar_utils::unit_conversion::*;
+
+///Neighbours closer than this many mutual Hill radii are not even Hill stable (Gladman 1993),
+///so they are bound to collide or scatter.
+pub const CRITICAL_HILL_SPACING: f64 = 3.4641016151377544;
+///Spacing that keeps multi-planet systems stable over billions of years (Chambers et al. 1996).
+pub const STABLE_HILL_SPACING: f64 = 10.0;
+///Prograde moons are only bound out to about half of their planet's Hill radius.
+pub const MOON_HILL_FRACTION: f64 = 0.5;
+
+#[derive(Clone, Debug)]
+pub enum StabilityIssue {
+    ///Two neighbouring bodies only this many mutual Hill radii apart.
+    HillSpacing { inner: u32, outer: u32, spacing: f64 },
+    ///The inner body's apoapsis reaches past the outer body's periapsis.
+    OrbitCrossing { inner: u32, outer: u32 },
+    ///A moon straying outside the stable part of its planet's Hill sphere. In meters.
+    OutsideHill { id: u32, apoapsis: f64, limit: f64 },
+    ///A body dipping inside its parent's Roche limit or surface. In meters.
+    InsideRoche { id: u32, periapsis: f64, limit: f64 },
+}
+
+#[derive(Clone, Debug)]
+pub enum Repair {
+    ///Semi-major axis moved, in meters.
+    Nudged { id: u32, from: f64, to: f64 },
+    ///Eccentricity damped so the orbit no longer crosses its neighbour's.
+    Circularized { id: u32, from: f64 },
+    ///Collided with and was absorbed into another body.
+    Merged { id: u32, into: u32 },
+    ///Flung out of the system by a close encounter, or stripped from its planet.
+    Ejected { id: u32 },
+    ///Torn apart by tides, with no room left outside the Roche limit.
+    Disrupted { id: u32 },
+}
+
+///What the stability pass found, what it did about it, and what is still wrong afterwards.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct StabilityReport {
+    pub issues: Vec<StabilityIssue>,
+    pub repairs: Vec<Repair>,
+    pub remaining: Vec<StabilityIssue>,
+}
+
+impl StabilityReport {
+    pub fn is_stable(&self) -> bool {
+        self.remaining.is_empty()
+    }
+}
+
+impl std::fmt::Display for StabilityReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} issues, {} repairs, {} remaining",
+            self.issues.len(), self.repairs.len(), self.remaining.len())?;
+
+        for repair in &self.repairs {
+            match repair {
+                Repair::Nudged { id, from, to } =>
+                    writeln!(f, " | ({id}) nudged from {} to {}", format_distance(*from), format_distance(*to))?,
+                Repair::Circularized { id, from } =>
+                    writeln!(f, " | ({id}) circularized from e={from:.2}")?,
+                Repair::Merged { id, into } => writeln!(f, " | ({id}) merged into ({into})")?,
+                Repair::Ejected { id } => writeln!(f, " | ({id}) ejected")?,
+                Repair::Disrupted { id } => writeln!(f, " | ({id}) disrupted")?,
+            }
+        }
+        for issue in &self.remaining {
+            writeln!(f, " | unresolved: {:?}", issue)?;
+        }
+
+        Ok(())
+    }
+}
+
+///The body a set of satellites orbits.
+struct Primary {
+    ///In kg.
+    mass: f64,
+    ///In meters.
+    radius: f64,
+    ///Outermost stable orbit in meters, if the primary is itself orbiting something.
+    outer_limit: Option<f64>,
+}
+
+impl Primary {
+    //parent mass is that of whatever this primary orbits, in kg
+    fn of(system: &MTree<CelestialBody>, id: u32, parent_mass: Option<f64>) -> Option<Self> {
+        match system.get_value(id)? {
+            CelestialBody::Star(star) => Some(Primary {
+                mass: sols(star.mass),
+                radius: star.radius * SOL_RADIUS,
+                outer_limit: None,
+            }),
+            CelestialBody::Planet(planet) => {
+                let mass = earths(planet.mass);
+                Some(Primary {
+                    mass,
+                    radius: planet.radius * 1000.0,
+                    outer_limit: parent_mass.map(|parent_mass|
+                        MOON_HILL_FRACTION * hill_radius(parent_mass, mass, planet.orbit.semi_major_axis())
+                    ),
+                })
+            },
+            CelestialBody::Barycenter(_) => None,
+        }
+    }
+
+    ///Closest a satellite of this density (kg/m^3) can get without being torn apart or touching the surface.
+    fn inner_limit(&self, density: f64) -> f64 {
+        roche_limit(self.mass, density).max(self.radius)
+    }
+}
+
+///Hill radius in meters of a body of mass (kg) orbiting a primary of primary_mass (kg) at semi_major (m).
+pub fn hill_radius(primary_mass: f64, mass: f64, semi_major: f64) -> f64 {
+    semi_major * (mass / (3.0 * primary_mass)).cbrt()
+}
+
+///Rigid body Roche limit in meters for a satellite of this density (kg/m^3) around a primary of this mass (kg).
+pub fn roche_limit(primary_mass: f64, density: f64) -> f64 {
+    (3.0 * primary_mass / (2.0 * PI * density)).cbrt()
+}
+
+///Separation of two neighbours in units of their mutual Hill radius.
+pub fn hill_spacing(primary_mass: f64, inner: &Planet, outer: &Planet) -> f64 {
+    let (a_in, a_out) = (inner.orbit.semi_major_axis(), outer.orbit.semi_major_axis());
+    let mutual = ((earths(inner.mass + outer.mass)) / (3.0 * primary_mass)).cbrt() * (a_in + a_out) / 2.0;
+
+    (a_out - a_in) / mutual
+}
+
+///Checks every orbit in the system without changing anything.
+pub fn check_stability(system: &MTree<CelestialBody>, min_spacing: f64) -> StabilityReport {
+    let mut issues = vec![];
+    check_satellites(system, system.root_id(), None, min_spacing, &mut issues);
+
+    StabilityReport { issues: issues.clone(), repairs: vec![], remaining: issues }
+}
+
+///Checks the system and then nudges, merges or ejects bodies until it is stable.
+pub fn repair_stability(system: &mut MTree<CelestialBody>, min_spacing: f64) -> StabilityReport {
+    let issues = check_stability(system, min_spacing).issues;
+
+    let mut repairs = vec![];
+    let root = system.root_id();
+    repair_satellites(system, root, None, min_spacing, &mut repairs);
+
+    let remaining = check_stability(system, min_spacing).issues;
+
+    StabilityReport { issues, repairs, remaining }
+}
+
+//planets directly orbiting this node, innermost first
+fn satellites(system: &MTree<CelestialBody>, id: u32) -> Vec<(u32, Planet)> {
+    let mut satellites: Vec<(u32, Planet)> = system.children(id).into_iter()
+        .filter_map(|child| match system.get_value(child) {
+            Some(CelestialBody::Planet(planet)) => Some((child, planet.clone())),
+            _ => None
+        })
+        .collect();
+
+    sort_by_distance(&mut satellites);
+
+    satellites
+}
+
+//innermost orbit first
+fn sort_by_distance(satellites: &mut [(u32, Planet)]) {
+    satellites.sort_by(|(_, a), (_, b)|
+        a.orbit.semi_major_axis().total_cmp(&b.orbit.semi_major_axis()));
+}
+
+fn check_satellites(
+    system: &MTree<CelestialBody>, id: u32, parent_mass: Option<f64>,
+    min_spacing: f64, issues: &mut Vec<StabilityIssue>
+) {
+    let Some(primary) = Primary::of(system, id, parent_mass) else { return };
+    let satellites = satellites(system, id);
+
+    for (id, planet) in &satellites {
+        let limit = primary.inner_limit(planet.density);
+        if planet.orbit.periapsis < limit {
+            issues.push(StabilityIssue::InsideRoche { id: *id, periapsis: planet.orbit.periapsis, limit });
+        }
+
+        if let Some(limit) = primary.outer_limit.filter(|limit| planet.orbit.apoapsis() > *limit) {
+            issues.push(StabilityIssue::OutsideHill { id: *id, apoapsis: planet.orbit.apoapsis(), limit });
+        }
+    }
+
+    for pair in satellites.windows(2) {
+        let ((inner_id, inner), (outer_id, outer)) = (&pair[0], &pair[1]);
+
+        if inner.orbit.apoapsis() >= outer.orbit.periapsis {
+            issues.push(StabilityIssue::OrbitCrossing { inner: *inner_id, outer: *outer_id });
+        }
+
+        let spacing = hill_spacing(primary.mass, inner, outer);
+        if spacing < min_spacing {
+            issues.push(StabilityIssue::HillSpacing { inner: *inner_id, outer: *outer_id, spacing });
+        }
+    }
+
+    for (id, _) in satellites {
+        check_satellites(system, id, Some(primary.mass), min_spacing, issues);
+    }
+}
+
+fn repair_satellites(
+    system: &mut MTree<CelestialBody>, id: u32, parent_mass: Option<f64>,
+    min_spacing: f64, repairs: &mut Vec<Repair>
+) {
+    let Some(primary) = Primary::of(system, id, parent_mass) else { return };
+    let outer_limit = primary.outer_limit.unwrap_or(f64::INFINITY);
+
+    //bounds first, so the spacing pass starts from legal orbits
+    for (id, mut planet) in satellites(system, id) {
+        let inner_limit = primary.inner_limit(planet.density);
+        let from = planet.orbit.semi_major_axis();
+
+        if planet.orbit.periapsis <= 0.0 {
+            planet.orbit.periapsis = inner_limit * 1.1;
+            planet.orbit.eccentricity = 0.0;
+        }
+        if planet.orbit.periapsis < inner_limit {
+            let factor = inner_limit * 1.1 / planet.orbit.periapsis;
+            scale_orbit(&mut planet, factor);
+        }
+        if planet.orbit.apoapsis() > outer_limit {
+            let factor = outer_limit * 0.9 / planet.orbit.apoapsis();
+            scale_orbit(&mut planet, factor);
+        }
+
+        //no room left between the two limits
+        if planet.orbit.periapsis < inner_limit {
+            let _ = system.remove(id);
+            repairs.push(Repair::Disrupted { id });
+            continue;
+        }
+        if planet.orbit.apoapsis() > outer_limit {
+            let _ = system.remove(id);
+            repairs.push(Repair::Ejected { id });
+            continue;
+        }
+
+        let to = planet.orbit.semi_major_axis();
+        if to != from {
+            set_planet(system, id, planet);
+            repairs.push(Repair::Nudged { id, from, to });
+        }
+    }
+
+    //then neighbours, working outward so that nudges can cascade
+    let mut satellites = satellites(system, id);
+    let mut i = 1;
+    while i < satellites.len() {
+        if satellites[i - 1].1.orbit.apoapsis() >= satellites[i].1.orbit.periapsis {
+            //encounters damp the eccentricities of crossing orbits long before anything collides
+            for j in [i - 1, i] {
+                let (id, planet) = &mut satellites[j];
+                let from = planet.orbit.eccentricity;
+                if from > 0.0 {
+                    planet.orbit.periapsis = planet.orbit.semi_major_axis();
+                    planet.orbit.eccentricity = 0.0;
+                    set_planet(system, *id, planet.clone());
+                    repairs.push(Repair::Circularized { id: *id, from });
+                }
+            }
+        }
+
+        let inner = satellites[i - 1].1.clone();
+        let (outer_id, mut outer) = satellites[i].clone();
+        let spacing = hill_spacing(primary.mass, &inner, &outer);
+
+        if spacing < CRITICAL_HILL_SPACING {
+            //the bigger body either swallows the smaller or, if it can throw things faster than
+            //their orbital speed (a Safronov number above 1), flings it away
+            let (winner, loser) = match inner.mass >= outer.mass {
+                true => (i - 1, i),
+                false => (i, i - 1),
+            };
+            let (winner_id, mut winner_planet) = satellites[winner].clone();
+            let (loser_id, loser_planet) = satellites[loser].clone();
+
+            let safronov = earths(winner_planet.mass) / primary.mass
+                * winner_planet.orbit.semi_major_axis() / (winner_planet.radius * 1000.0);
+
+            if safronov > 1.0 {
+                repairs.push(Repair::Ejected { id: loser_id });
+            }
+            else {
+                winner_planet.mass += loser_planet.mass;
+                winner_planet.radius = (3.0 * earths(winner_planet.mass)
+                    / (4.0 * PI * winner_planet.density)).cbrt() / 1000.0;
+                set_planet(system, winner_id, winner_planet.clone());
+                satellites[winner].1 = winner_planet;
+                repairs.push(Repair::Merged { id: loser_id, into: winner_id });
+            }
+
+            let _ = system.remove(loser_id);
+            satellites.remove(loser);
+            //compare the survivor against its new inner neighbour
+            i = (i - 1).max(1);
+            continue;
+        }
+
+        if spacing < min_spacing {
+            //solve for the semi-major axis that puts the pair min_spacing mutual Hill radii apart,
+            //aiming slightly past it so rounding cannot leave them short
+            let x = 1.001 * min_spacing * (earths(inner.mass + outer.mass) / (3.0 * primary.mass)).cbrt() / 2.0;
+            let from = outer.orbit.semi_major_axis();
+            let to = inner.orbit.semi_major_axis() * (1.0 + x) / (1.0 - x);
+
+            //too massive to ever fit, or pushed out of the primary's reach
+            if x >= 1.0 || outer.orbit.apoapsis() * to / from > outer_limit {
+                let _ = system.remove(outer_id);
+                satellites.remove(i);
+                repairs.push(Repair::Ejected { id: outer_id });
+                continue;
+            }
+
+            scale_orbit(&mut outer, to / from);
+            set_planet(system, outer_id, outer.clone());
+            satellites[i].1 = outer;
+            repairs.push(Repair::Nudged { id: outer_id, from, to });
+
+            //pushed out past its next neighbour, so whatever takes its place goes up against the same inner body
+            if satellites.get(i + 1).is_some_and(|(_, next)| next.orbit.semi_major_axis() < to) {
+                sort_by_distance(&mut satellites);
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+
+    for (id, _) in satellites {
+        repair_satellites(system, id, Some(primary.mass), min_spacing, repairs);
+    }
+}
+
+//stretches an orbit by this factor, keeping its shape
+fn scale_orbit(planet: &mut Planet, factor: f64) {
+    planet.orbit.periapsis *= factor;
+}
+
+fn set_planet(system: &mut MTree<CelestialBody>, id: u32, planet: Planet) {
+    if let Some(CelestialBody::Planet(value)) = system.get_value_mut(id) {
+        *value = planet;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stellar_core::solar_system::{Orbit, Star};
+
+    //an earth on a circular orbit of this radius (m)
+    fn earth(semi_major: f64) -> CelestialBody {
+        CelestialBody::Planet(Planet {
+            mass: 1.0,
+            final_mass: 1.0,
+            density: 5514.0,
+            radius: 6371.0,
+            orbit: Orbit::from_elements(semi_major, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn repairs_crowded_planets_and_a_moon_inside_the_roche_limit() {
+        let mut system = MTree::new(CelestialBody::Star(Star::new(1.0, 4.6, 1.0)));
+        let root = system.root_id();
+
+        //two mutual Hill radii apart, well short of even Hill stability
+        let a = au(1.0);
+        let k = (earths(2.0) / (3.0 * sols(1.0))).cbrt();
+        let inner = system.append(root, earth(a)).unwrap();
+        let outer = system.append(root, earth(a * (1.0 + k) / (1.0 - k))).unwrap();
+        //and a moon just above the inner planet's surface, inside its Roche limit
+        let moon = system.append(inner, earth(7.0e6)).unwrap();
+
+        let report = repair_stability(&mut system, STABLE_HILL_SPACING);
+
+        assert!(report.issues.iter().any(|issue| matches!(issue,
+            StabilityIssue::HillSpacing { inner: i, outer: o, spacing } if (*i, *o) == (inner, outer) && (spacing - 2.0).abs() < 1e-6
+        )), "{report}");
+        assert!(report.issues.iter().any(|issue| matches!(issue,
+            StabilityIssue::InsideRoche { id, .. } if *id == moon
+        )), "{report}");
+
+        //that close they collide, so the inner planet takes the outer, and the moon is pushed out past the limit
+        assert!(report.repairs.iter().any(|repair| matches!(repair,
+            Repair::Merged { id, into } if (*id, *into) == (outer, inner)
+        )), "{report}");
+        assert!(report.repairs.iter().any(|repair| matches!(repair,
+            Repair::Nudged { id, .. } if *id == moon
+        )), "{report}");
+
+        assert!(report.is_stable(), "{report}");
+        assert!(check_stability(&system, STABLE_HILL_SPACING).is_stable());
+    }
+}
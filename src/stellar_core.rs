@@ -4,6 +4,8 @@ pub mod ship;
 pub mod navigation;
 pub mod solar_system;
 pub mod camera;
+pub mod clock;
+pub mod floating_origin;
 
 //im not sure if refactoring the utilities into the mod here is right,
 //but here they are!
@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+
+///Warp rates the controls step through. Everything above MAX_PHYSICS_WARP is rails warp.
+pub const WARP_LEVELS: [f64; 7] = [1.0, 10.0, 100.0, 1_000.0, 10_000.0, 100_000.0, 1_000_000.0];
+///Fastest warp at which the ship still takes player input.
+pub const MAX_PHYSICS_WARP: f64 = 10.0;
+///How much faster than everything else stars can be made to age. Even at full warp a star
+///takes a real year to age a million years, so evolution needs its own speed-up to be seen.
+pub const EVOLUTION_SCALES: [f64; 4] = [1.0, 1_000.0, 1_000_000.0, 1_000_000_000.0];
+
+pub struct ClockPlugin;
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<SimulationClock>()
+            //time only moves on the fixed timestep, so the ship and the bodies can't drift apart
+            .add_systems(First, SimulationClock::start_frame)
+            .add_systems(FixedFirst, SimulationClock::tick)
+            .add_systems(Update, SimulationClock::controls)
+            ;
+    }
+}
+
+///Simulated time, separate from the frame clock so it can be paused and warped.
+///All times are in seconds.
+#[derive(Resource, Debug)]
+pub struct SimulationClock {
+    ///Simulation time at which the mission started.
+    pub epoch: f64,
+    ///Simulated time since the epoch.
+    pub elapsed: f64,
+    ///Simulated seconds per real second.
+    pub warp: f64,
+    pub paused: bool,
+    ///Stellar evolution time per simulated second, one of EVOLUTION_SCALES.
+    pub evolution_scale: f64,
+    //simulated time that passed this frame
+    delta: f64,
+    //elapsed before the last fixed step
+    previous: f64,
+}
+
+impl Default for SimulationClock {
+    fn default() -> Self {
+        SimulationClock {
+            epoch: 0.0, elapsed: 0.0, warp: 1.0, paused: false, evolution_scale: 1.0, delta: 0.0, previous: 0.0
+        }
+    }
+}
+
+impl SimulationClock {
+    ///Current simulation time, which is what orbits are evaluated at.
+    pub fn time(&self) -> f64 {
+        self.epoch + self.elapsed
+    }
+
+    ///Where the simulation time is drawn, between the last two fixed steps, so the bodies
+    ///are interpolated by the same overstep as the ship.
+    pub fn render_time(&self, overstep_fraction: f64) -> f64 {
+        self.epoch + self.previous + (self.elapsed - self.previous) * overstep_fraction
+    }
+
+    ///Simulated time that passed during the last frame. Zero while paused.
+    pub fn delta_secs(&self) -> f64 {
+        self.delta
+    }
+
+    ///Time stars and their disks aged during the last frame, sped up by the evolution scale.
+    pub fn evolution_delta_secs(&self) -> f64 {
+        self.delta * self.evolution_scale
+    }
+
+    ///True when warping too fast for the ship to be flown.
+    pub fn on_rails(&self) -> bool {
+        self.warp > MAX_PHYSICS_WARP
+    }
+
+    ///Steps the warp up or down by this many levels.
+    pub fn step_warp(&mut self, steps: i32) {
+        let current = WARP_LEVELS.iter()
+            .position(|&level| level >= self.warp)
+            .unwrap_or(WARP_LEVELS.len() - 1) as i32;
+        let next = (current + steps).clamp(0, WARP_LEVELS.len() as i32 - 1);
+
+        self.warp = WARP_LEVELS[next as usize];
+    }
+
+    ///Steps the evolution scale up or down by this many levels.
+    pub fn step_evolution_scale(&mut self, steps: i32) {
+        let current = EVOLUTION_SCALES.iter()
+            .position(|&scale| scale >= self.evolution_scale)
+            .unwrap_or(EVOLUTION_SCALES.len() - 1) as i32;
+        let next = (current + steps).clamp(0, EVOLUTION_SCALES.len() as i32 - 1);
+
+        self.evolution_scale = EVOLUTION_SCALES[next as usize];
+    }
+
+    pub fn start_frame(mut clock: ResMut<SimulationClock>) {
+        clock.delta = 0.0;
+    }
+
+    ///Advances the clock by one fixed step, before anything in FixedUpdate runs.
+    pub fn tick(time: Res<Time<Fixed>>, mut clock: ResMut<SimulationClock>) {
+        let step = match clock.paused {
+            true => 0.0,
+            false => time.delta_secs_f64() * clock.warp,
+        };
+        clock.previous = clock.elapsed;
+        clock.elapsed += step;
+        clock.delta += step;
+    }
+
+    ///Period and comma warp faster and slower, slash drops back to 1x, P pauses.
+    ///Equals and minus speed stellar evolution up and down.
+    pub fn controls(keyboard: Res<ButtonInput<KeyCode>>, mut clock: ResMut<SimulationClock>) {
+        if keyboard.just_pressed(KeyCode::Period) {
+            clock.step_warp(1);
+        }
+        if keyboard.just_pressed(KeyCode::Comma) {
+            clock.step_warp(-1);
+        }
+        if keyboard.just_pressed(KeyCode::Slash) {
+            clock.warp = 1.0;
+        }
+        if keyboard.just_pressed(KeyCode::KeyP) {
+            clock.paused = !clock.paused;
+        }
+        if keyboard.just_pressed(KeyCode::Equal) {
+            clock.step_evolution_scale(1);
+        }
+        if keyboard.just_pressed(KeyCode::Minus) {
+            clock.step_evolution_scale(-1);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+use bevy::math::DVec2;
+use bevy::transform::TransformSystem;
+
+use crate::stellar_core::camera::CamChase;
+use crate::stellar_core::ship::Ship;
+use crate::stellar_core::solar_system::{to_world, to_meters};
+
+pub struct FloatingOriginPlugin;
+impl Plugin for FloatingOriginPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<FloatingOrigin>()
+            //after everything has moved this frame, but before bevy works out the global transforms
+            .add_systems(PostUpdate, FloatingOrigin::recentre.before(TransformSystem::TransformPropagate))
+            ;
+    }
+}
+
+///Authoritative position in meters. The Transform of anything with one is derived from it every frame.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct WorldPosition(pub DVec2);
+
+//impl deref so you can refer to it as *position instead of position.0
+impl std::ops::Deref for WorldPosition {
+    type Target = DVec2;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for WorldPosition {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+///Where the render origin sits in the world, in meters.
+///Transforms are relative to this, so f32 precision is always best right where the camera is looking.
+#[derive(Resource, Debug, Default)]
+pub struct FloatingOrigin {
+    pub position: DVec2,
+}
+
+impl FloatingOrigin {
+    ///Where a position in meters ends up on screen, in world units.
+    pub fn to_render(&self, position: DVec2) -> Vec2 {
+        to_world(position - self.position)
+    }
+
+    ///Moves the origin onto the ship, or wherever the free camera went, then rebuilds every Transform from its WorldPosition.
+    pub fn recentre(
+        mut origin: ResMut<FloatingOrigin>,
+        mut camera: Query<(&mut Transform, Has<CamChase>), With<Camera2d>>,
+        ship: Query<&WorldPosition, With<Ship>>,
+        mut positioned: Query<(&WorldPosition, &mut Transform), Without<Camera2d>>,
+    ) {
+        if let Ok((mut transform, chasing)) = camera.get_single_mut() {
+            match (chasing, ship.get_single()) {
+                (true, Ok(ship)) => origin.position = **ship,
+                _ => origin.position += to_meters(transform.translation.xy()),
+            }
+
+            //the camera always sits on the origin
+            transform.translation.x = 0.0;
+            transform.translation.y = 0.0;
+        }
+
+        for (position, mut transform) in positioned.iter_mut() {
+            let render = origin.to_render(**position);
+            transform.translation.x = render.x;
+            transform.translation.y = render.y;
+        }
+    }
+}
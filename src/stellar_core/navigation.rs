@@ -1,54 +1,61 @@
-use core::f64;
-use std::f64::consts::PI;
-
 use bevy::prelude::*;
-use super::solar_system::celestial_body::Star;
-use super::solar_system::Mass;
-use crate::stellar_utils::unit_conversion::*;
-
-pub const G: f64 = 6.6743015e-11;
-const SOL_MASS: f64 = 2e14;
-
-//calculate the total acceleration at a given position from a vec of celestial bodies.
-pub fn calculate_acceleration(
-    position: &Vec2, bodies: &Vec<(&Mass, &bevy::prelude::Transform)>, 
-    stars: &Vec<(&Star, &bevy::prelude::Transform)>
-) -> Vec2 {
-
-    //start with zero accel
-    let mut accel = Vec2::new(0.0,0.0);
-
-    //iterate through each body, adding the acceleration together.
-    for (mass, transform) in bodies {
-        accel += acceleration(
-            &transform.translation.xy(), 
-            position, 
-            ***mass, 
-            f64::powf((3.0 * ***mass) / (4.0 * PI * 2000.0), 1.0 / 3.0) );
-    }
+use bevy::math::DVec2;
+use super::solar_system::{Ephemeris, orbit::G};
+
+pub mod integrator;
+pub use integrator::Integrator;
+
+pub mod patched_conics;
+pub use patched_conics::{Patch, SoiChange};
+
+pub mod collision;
+pub use collision::{Contact, ContactKind};
+
+pub mod drag;
+pub use drag::Airframe;
+
+pub mod radiation;
+pub use radiation::SolarSail;
+
+pub mod maneuver;
+pub use maneuver::ManeuverNode;
 
-    accel
+pub mod markers;
+pub use markers::{Marker, MarkerKind};
+
+pub mod transfer;
+pub use transfer::{Transfer, TransferRequest};
+
+pub mod porkchop;
+pub use porkchop::PorkchopGrid;
+
+pub mod flyby;
+pub use flyby::{Flyby, Trajectory};
+
+pub mod prediction;
+pub use prediction::{Prediction, PredictionConfig, PredictionTask};
+
+///Softening length that turns softening off, so gravity is plain Newtonian.
+pub const NO_SOFTENING: f64 = 0.0;
+
+//total acceleration (m/s²) at a position in meters from every body, with each one where its orbit puts it at time t (s).
+//softening is a length in meters that smooths out the singularity at each body's centre. pass NO_SOFTENING to leave it off
+pub fn ephemeris_acceleration(ephemeris: &Ephemeris, position: DVec2, t: f64, softening: f64) -> DVec2 {
+    ephemeris.iter()
+        .filter_map(|(entity, body)| Some(acceleration(ephemeris.position_at(entity, t)?, position, body.mass, softening)))
+        .sum()
 }
 
-//modified newton's. Ignores mass of one of the objects, and adds a repulsive force when close by
-pub fn acceleration(pos1: &Vec2, pos2: &Vec2, mass: f64, radius: f64) -> Vec2 {
+//newton's law of gravitation, with the mass of the object being pulled ignored.
+//pos1 is the attracting body of this mass (kg), pos2 is where the acceleration is felt, both in meters
+pub fn acceleration(pos1: DVec2, pos2: DVec2, mass: f64, softening: f64) -> DVec2 {
     let delta_pos = pos1 - pos2;
-    let mass = mass * 1e-10;
 
-    //true distance is split into two calculations  since we want to check for zero
-    let distance_squared = delta_pos.length_squared() as f64;
+    //plummer softening: for distances well beyond the softening length it's the same as newton's
+    let distance_squared = delta_pos.length_squared() + softening * softening;
     if distance_squared == 0.0 { 
-        return Vec2::splat(0.0); //avoid division by zero
+        return DVec2::ZERO; //avoid division by zero
     }
 
-    let distance = distance_squared.sqrt() as f64;
-    //calculate magnitude + incorporate repulsive force calculation
-    let soft = radius * 0.1;
-    let acceleration_magnitude = G * mass * (distance_squared - soft * soft) / (distance_squared * distance_squared);
-
-    let direction = delta_pos / distance as f32;
-    //cap it to at minimum -0.1
-    let acceleration = direction * f64::max(-0.1, acceleration_magnitude) as f32;
-
-    return acceleration;
+    delta_pos * G * mass / (distance_squared * distance_squared.sqrt())
 }
\ No newline at end of file
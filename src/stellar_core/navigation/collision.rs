@@ -0,0 +1,102 @@
+//Contact with the surfaces of bodies, and whether it was a landing or a crash.
+
+use bevy::prelude::*;
+use bevy::math::DVec2;
+
+use super::integrator::State;
+use crate::stellar_core::solar_system::Ephemeris;
+
+///Fastest touchdown (m/s, relative to the surface) that the ship walks away from.
+pub const SOFT_LANDING_SPEED: f64 = 5.0;
+///Fastest touchdown (m/s) the ship survives at all.
+pub const HARD_LANDING_SPEED: f64 = 20.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContactKind {
+    SoftLanding,
+    HardLanding,
+    Crash,
+}
+
+impl ContactKind {
+    ///What touching down at this speed (m/s) does to the ship. Nothing lands on a star.
+    pub fn classify(speed: f64, solid: bool) -> ContactKind {
+        match speed {
+            _ if !solid => ContactKind::Crash,
+            s if s <= SOFT_LANDING_SPEED => ContactKind::SoftLanding,
+            s if s <= HARD_LANDING_SPEED => ContactKind::HardLanding,
+            _ => ContactKind::Crash,
+        }
+    }
+
+    ///Short label for the HUD.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ContactKind::SoftLanding => "LANDING",
+            ContactKind::HardLanding => "HARD LANDING",
+            ContactKind::Crash => "CRASH",
+        }
+    }
+}
+
+///The ship touching a body's surface.
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+    pub body: Entity,
+    pub kind: ContactKind,
+    ///Simulation time of the touchdown, in seconds.
+    pub time: f64,
+    ///Where the ship touched down, in meters.
+    pub position: DVec2,
+    ///Unit vector from the body's centre to the touchdown point.
+    pub normal: DVec2,
+    ///Speed relative to the surface, in m/s.
+    pub speed: f64,
+}
+
+///First surface the ship runs into moving from one state at time t to another dt later.
+///The ship is taken to move in a straight line relative to each body, so fast passes can't tunnel through.
+pub fn find_contact(ephemeris: &Ephemeris, from: State, to: State, t: f64, dt: f64) -> Option<Contact> {
+    ephemeris.iter()
+        .filter_map(|(entity, body)| {
+            let (start, _) = ephemeris.state_at(entity, t)?;
+            let (end, _) = ephemeris.state_at(entity, t + dt)?;
+
+            let before = from.position - start;
+            let travel = (to.position - end) - before;
+
+            //first point along the way inside the radius: |before + s travel| = radius
+            let a = travel.length_squared();
+            let b = 2.0 * before.dot(travel);
+            let c = before.length_squared() - body.radius * body.radius;
+            let s = match c <= 0.0 {
+                true => 0.0,
+                false if a == 0.0 => return None,
+                false => {
+                    let discriminant = b * b - 4.0 * a * c;
+                    if discriminant < 0.0 {
+                        return None;
+                    }
+                    (-b - discriminant.sqrt()) / (2.0 * a)
+                },
+            };
+            if !(0.0..=1.0).contains(&s) {
+                return None;
+            }
+
+            let time = t + s * dt;
+            let (centre, surface_velocity) = ephemeris.state_at(entity, time)?;
+            let normal = (before + travel * s).normalize_or_zero();
+            let speed = (from.velocity.lerp(to.velocity, s) - surface_velocity).length();
+
+            Some(Contact {
+                body: entity,
+                kind: ContactKind::classify(speed, body.solid),
+                time,
+                position: centre + normal * body.radius,
+                normal,
+                speed,
+            })
+        })
+        .min_by(|a, b| a.time.total_cmp(&b.time))
+}
@@ -0,0 +1,109 @@
+//Atmospheric drag, and the heat it dumps into the ship on the way down.
+
+use bevy::prelude::*;
+use bevy::math::DVec2;
+
+use super::integrator::State;
+use super::radiation::SolarSail;
+use crate::stellar_core::solar_system::Ephemeris;
+use crate::stellar_utils::unit_conversion::STEFAN_BOLTZMANN;
+
+///Sutton-Graves constant for stagnation point heating in air, in kg^0.5/m.
+const SUTTON_GRAVES: f64 = 1.7415e-4;
+///Temperature (K) the skin cools towards out of the air.
+pub const AMBIENT_TEMPERATURE: f64 = 280.0;
+
+///What the ship looks like to the air and to starlight.
+#[derive(Clone, Copy, Debug)]
+pub struct Airframe {
+    ///Mass in kg.
+    pub mass: f64,
+    ///Cross-section facing the airflow and the light, in m².
+    pub area: f64,
+    pub drag_coefficient: f64,
+    ///Fraction of the light falling on the hull that's reflected rather than absorbed.
+    pub reflectivity: f64,
+    ///The solar sail, while it's deployed.
+    pub sail: Option<SolarSail>,
+    ///Radius of curvature of the heat shield, in meters. Blunter shields heat up less.
+    pub nose_radius: f64,
+    ///Heat (J) it takes to warm a square meter of skin by one kelvin.
+    pub heat_capacity: f64,
+    ///Skin temperature (K) the heat shield is rated for.
+    pub max_temperature: f64,
+}
+
+impl Default for Airframe {
+    //roughly a crew capsule
+    fn default() -> Self {
+        Airframe {
+            mass: 10_000.0,
+            area: 12.0,
+            drag_coefficient: 1.3,
+            reflectivity: 0.3,
+            sail: None,
+            nose_radius: 4.0,
+            heat_capacity: 50_000.0,
+            max_temperature: 1900.0,
+        }
+    }
+}
+
+impl Airframe {
+    ///Mass per unit of drag area, kg/m². The higher it is, the less the air slows the ship.
+    pub fn ballistic_coefficient(&self) -> f64 {
+        self.mass / (self.drag_coefficient * self.area)
+    }
+
+    ///Heat flowing into the nose (W/m²) flying at this speed (m/s) through air of this density (kg/m³).
+    pub fn heat_flux(&self, density: f64, speed: f64) -> f64 {
+        SUTTON_GRAVES * (density / self.nose_radius).sqrt() * speed.powi(3)
+    }
+
+    ///Skin temperature (K) after dt seconds under this heat flux (W/m²), radiating the heat away as it goes.
+    ///The skin relaxes towards the temperature where the two balance, so long steps can't overshoot.
+    pub fn skin_temperature(&self, temperature: f64, heat_flux: f64, dt: f64) -> f64 {
+        let balance = (heat_flux / STEFAN_BOLTZMANN + AMBIENT_TEMPERATURE.powi(4)).powf(0.25);
+        let time_constant = self.heat_capacity / (4.0 * STEFAN_BOLTZMANN * balance.powi(3));
+
+        balance + (temperature - balance) * (-dt / time_constant).exp()
+    }
+}
+
+///The air the ship is flying through.
+#[derive(Clone, Copy, Debug)]
+pub struct Air {
+    pub body: Entity,
+    ///Density in kg/m³.
+    pub density: f64,
+    ///Velocity of the ship relative to the air, in m/s. The air turns with its planet, which doesn't spin.
+    pub velocity: DVec2,
+    ///Scale height of the atmosphere in meters, which sets how quickly all this changes on the way down.
+    pub scale_height: f64,
+}
+
+///The air at this state at time t, or None out in vacuum.
+pub fn air_at(ephemeris: &Ephemeris, state: State, t: f64) -> Option<Air> {
+    ephemeris.iter()
+        .filter_map(|(entity, body)| {
+            let atmosphere = body.atmosphere?;
+            let (centre, velocity) = ephemeris.state_at(entity, t)?;
+
+            let density = atmosphere.density_at(state.position.distance(centre) - body.radius);
+            (density > 0.0).then_some(Air {
+                body: entity, density, velocity: state.velocity - velocity, scale_height: atmosphere.scale_height
+            })
+        })
+        .max_by(|a, b| a.density.total_cmp(&b.density))
+}
+
+///The state after dt seconds of drag. The airflow's direction is held for the step, and the speed along it
+///is solved exactly for quadratic drag, so the air can slow the ship to a standstill but never turn it around.
+pub fn apply_drag(ephemeris: &Ephemeris, airframe: &Airframe, state: State, t: f64, dt: f64) -> State {
+    let Some(air) = air_at(ephemeris, state, t) else { return state };
+
+    let k = 0.5 * air.density / airframe.ballistic_coefficient();
+    let slowed = air.velocity / (1.0 + k * air.velocity.length() * dt);
+
+    State::new(state.position, state.velocity + slowed - air.velocity)
+}
@@ -0,0 +1,320 @@
+//Gravity assists: hyperbolic flybys that turn the ship's velocity for free, and a search for sequences
+//of them between two bodies that share a parent.
+
+use bevy::prelude::*;
+use bevy::math::DVec2;
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
+
+use super::transfer::{hohmann, lambert, TransferRequest};
+use crate::stellar_core::solar_system::{Ephemeris, Orbit, orbit::G};
+
+///Closest a flyby may pass, in radii of the body, on top of any atmosphere.
+pub const MIN_FLYBY_RADII: f64 = 1.1;
+///Flight times tried for each leg, as fractions of the Hohmann flight time between the two bodies.
+const LEG_TIME_RANGE: (f64, f64) = (0.3, 1.7);
+///Bisection steps when fitting a powered flyby's periapsis.
+const PERIAPSIS_ITERATIONS: u32 = 60;
+
+///Angle (rad) a hyperbolic flyby turns the velocity through, passing periapsis (m) of a body with
+///gravitational parameter mu (m³/s²) at an excess speed v_infinity (m/s).
+pub fn turn_angle(mu: f64, periapsis: f64, v_infinity: f64) -> f64 {
+    2.0 * (1.0 / (1.0 + periapsis * v_infinity.powi(2) / mu)).asin()
+}
+
+///A pass by a body on the way somewhere else.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Flyby {
+    pub body: Entity,
+    ///Simulation time of closest approach, in seconds.
+    pub time: f64,
+    ///Closest approach to the body's centre, in meters.
+    pub periapsis: f64,
+    ///Angle the velocity relative to the body turns through, in radians.
+    pub turn: f64,
+    ///Change of speed (m/s) at periapsis when the excess speeds in and out differ. Negative to slow down.
+    pub burn: f64,
+}
+
+impl Flyby {
+    ///Fits the flyby that takes the excess velocity v_in (m/s, relative to the body) to v_out, past a body
+    ///with gravitational parameter mu (m³/s²). If the speeds differ the difference is burned at periapsis.
+    ///None if it would have to pass closer than min_periapsis (m), or if the velocity doesn't turn at all,
+    ///since that's a burn rather than a flyby.
+    pub fn fit(mu: f64, min_periapsis: f64, body: Entity, time: f64, v_in: DVec2, v_out: DVec2) -> Option<Flyby> {
+        let (a, b) = (v_in.length(), v_out.length());
+        if a == 0.0 || b == 0.0 {
+            return None;
+        }
+        let turn = v_in.angle_to(v_out).abs();
+        if turn == 0.0 {
+            return None;
+        }
+
+        //each half of the hyperbola turns by half of what a flyby at its own excess speed would,
+        //and the total falls as periapsis rises
+        let turned = |periapsis: f64| (turn_angle(mu, periapsis, a) + turn_angle(mu, periapsis, b)) / 2.0;
+        if turned(min_periapsis) < turn {
+            return None;
+        }
+
+        let mut low = min_periapsis;
+        let mut high = min_periapsis * 2.0;
+        while turned(high) > turn {
+            high *= 2.0;
+            if !high.is_finite() {
+                return None;
+            }
+        }
+        for _ in 0..PERIAPSIS_ITERATIONS {
+            let middle = (low + high) / 2.0;
+            match turned(middle) > turn {
+                true => low = middle,
+                false => high = middle,
+            }
+        }
+        let periapsis = (low + high) / 2.0;
+
+        let speed = |v: f64| (v * v + 2.0 * mu / periapsis).sqrt();
+        Some(Flyby { body, time, periapsis, turn, burn: speed(b) - speed(a) })
+    }
+}
+
+///A way from an orbit to a body, past any number of others.
+#[derive(Clone, Debug)]
+pub struct Trajectory {
+    ///The bodies flown past, then the destination.
+    pub bodies: Vec<Entity>,
+    ///Simulation time of the departure burn, in seconds.
+    pub departure: f64,
+    ///Change of velocity (m/s) leaving the origin orbit, relative to the parent.
+    pub departure_burn: DVec2,
+    pub flybys: Vec<Flyby>,
+    ///Simulation time of arrival, in seconds.
+    pub arrival: f64,
+    ///Delta-v (m/s) to match the destination's velocity on arrival.
+    pub arrival_burn: f64,
+}
+
+impl Trajectory {
+    ///Delta-v of every burn together, in m/s.
+    pub fn delta_v(&self) -> f64 {
+        self.departure_burn.length() + self.flybys.iter().map(|flyby| flyby.burn.abs()).sum::<f64>() + self.arrival_burn
+    }
+}
+
+//the rest of a trajectory from a body it has just reached
+struct Continuation {
+    flybys: Vec<Flyby>,
+    arrival: f64,
+    arrival_burn: f64,
+    cost: f64,
+}
+
+///Searches every order of flybys, up to max_flybys long, past the via bodies on the way through the requested
+///transfer. Departure times and each leg's flight time, over a range about its Hohmann time, run in this many steps each.
+///The cheapest trajectory for each order, cheapest first. Going straight there is one of them.
+pub fn search(
+    ephemeris: &Ephemeris, request: &TransferRequest, via: &[Entity], max_flybys: usize, resolution: usize
+) -> Vec<Trajectory> {
+    let TransferRequest { parent, ref origin, origin_mass, destination, departures } = *request;
+    if resolution < 2 || ephemeris.parent(destination) != Some(parent) {
+        return Vec::new();
+    }
+
+    let mut sequences = vec![Vec::new()];
+    let mut last = vec![Vec::new()];
+    for _ in 0..max_flybys {
+        last = last.iter()
+            .flat_map(|sequence: &Vec<Entity>| via.iter()
+                .filter(|body| ephemeris.parent(**body) == Some(parent) && **body != destination)
+                //the same body twice running is no flyby at all
+                .filter(|body| sequence.last() != Some(*body))
+                .map(move |body| [sequence.as_slice(), &[*body]].concat()))
+            .collect();
+        sequences.extend(last.iter().cloned());
+    }
+
+    let mut trajectories: Vec<Trajectory> = sequences.into_iter()
+        .filter_map(|mut bodies| {
+            bodies.push(destination);
+            Search { ephemeris, parent, bodies, resolution }.best(origin, origin_mass, departures)
+        })
+        .collect();
+    trajectories.sort_by(|a, b| a.delta_v().total_cmp(&b.delta_v()));
+
+    trajectories
+}
+
+//one order of bodies to search the timings of
+struct Search<'a> {
+    ephemeris: &'a Ephemeris,
+    parent: Entity,
+    bodies: Vec<Entity>,
+    resolution: usize,
+}
+
+impl Search<'_> {
+    fn best(&self, origin: &Orbit, origin_mass: f64, (first, last): (f64, f64)) -> Option<Trajectory> {
+        let parent_mass = self.ephemeris.mass(self.parent);
+        let mu = G * parent_mass;
+        let next = self.bodies[0];
+        let flight_times = self.flight_times(origin.semi_major_axis(), next)?;
+
+        let mut best: Option<Trajectory> = None;
+        for i in 0..self.resolution {
+            let departure = first + (last - first) * i as f64 / (self.resolution - 1) as f64;
+            let (position, velocity) = origin.state_at_time(departure, origin_mass, parent_mass);
+
+            for &flight_time in &flight_times {
+                let Some((target, _)) = self.state_at(next, departure + flight_time) else { continue };
+                let Some((leave, arrive)) = lambert(mu, position.truncate(), target, flight_time) else { continue };
+                let Some(rest) = self.continue_from(0, departure + flight_time, arrive) else { continue };
+
+                let departure_burn = leave - velocity.truncate();
+                if best.as_ref().is_some_and(|best| best.delta_v() <= departure_burn.length() + rest.cost) {
+                    continue;
+                }
+
+                best = Some(Trajectory {
+                    bodies: self.bodies.clone(),
+                    departure,
+                    departure_burn,
+                    flybys: rest.flybys,
+                    arrival: rest.arrival,
+                    arrival_burn: rest.arrival_burn,
+                });
+            }
+        }
+
+        best
+    }
+
+    //cheapest way through the rest of the bodies, having reached bodies[index] at time t with velocity (m/s)
+    //relative to the parent
+    fn continue_from(&self, index: usize, t: f64, velocity: DVec2) -> Option<Continuation> {
+        let body = self.bodies[index];
+        let (position, body_velocity) = self.state_at(body, t)?;
+
+        let Some(&next) = self.bodies.get(index + 1) else {
+            let arrival_burn = (body_velocity - velocity).length();
+            return Some(Continuation { flybys: Vec::new(), arrival: t, arrival_burn, cost: arrival_burn });
+        };
+
+        let mu = G * self.ephemeris.mass(self.parent);
+        let body_mu = G * self.ephemeris.mass(body);
+        let min_periapsis = self.ephemeris.body(body).map_or(0.0, |body| {
+            MIN_FLYBY_RADII * body.radius + body.atmosphere.map_or(0.0, |atmosphere| atmosphere.height())
+        });
+        let radius = self.ephemeris.body(body)?.orbit.as_ref()?.semi_major_axis();
+
+        let mut best: Option<Continuation> = None;
+        for flight_time in self.flight_times(radius, next)? {
+            let Some((target, _)) = self.state_at(next, t + flight_time) else { continue };
+            let Some((leave, arrive)) = lambert(mu, position, target, flight_time) else { continue };
+            let v_in = velocity - body_velocity;
+            let Some(flyby) = Flyby::fit(body_mu, min_periapsis, body, t, v_in, leave - body_velocity) else { continue };
+            let Some(mut rest) = self.continue_from(index + 1, t + flight_time, arrive) else { continue };
+
+            let cost = flyby.burn.abs() + rest.cost;
+            if best.as_ref().is_some_and(|best| best.cost <= cost) {
+                continue;
+            }
+
+            rest.flybys.insert(0, flyby);
+            best = Some(Continuation { cost, ..rest });
+        }
+
+        best
+    }
+
+    //flight times to try from an orbit of this radius (m) out to the next body
+    fn flight_times(&self, radius: f64, next: Entity) -> Option<Vec<f64>> {
+        let to = self.ephemeris.body(next)?.orbit.as_ref()?.semi_major_axis();
+        let hohmann = hohmann(G * self.ephemeris.mass(self.parent), radius, to).flight_time;
+        let (first, last) = (LEG_TIME_RANGE.0 * hohmann, LEG_TIME_RANGE.1 * hohmann);
+
+        Some((0..self.resolution).map(|i| first + (last - first) * i as f64 / (self.resolution - 1) as f64).collect())
+    }
+
+    //position (m) and velocity (m/s) relative to the parent
+    fn state_at(&self, body: Entity, t: f64) -> Option<(DVec2, DVec2)> {
+        let (position, velocity) = self.ephemeris.state_at(body, t)?;
+        let (centre, centre_velocity) = self.ephemeris.state_at(self.parent, t)?;
+
+        Some((position - centre, velocity - centre_velocity))
+    }
+}
+
+///A flyby search running in the background, since there are a lot of Lambert problems in one.
+pub struct FlybySearchTask(Task<Vec<Trajectory>>);
+
+impl FlybySearchTask {
+    ///Starts searching on the async compute pool, from a snapshot of the ephemeris.
+    pub fn spawn(
+        ephemeris: Ephemeris, request: TransferRequest, via: Vec<Entity>, max_flybys: usize, resolution: usize
+    ) -> FlybySearchTask {
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            search(&ephemeris, &request, &via, max_flybys, resolution)
+        });
+
+        FlybySearchTask(task)
+    }
+
+    ///The trajectories, if the search is done.
+    pub fn poll(&mut self) -> Option<Vec<Trajectory>> {
+        block_on(poll_once(&mut self.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stellar_utils::unit_conversion::{au, sols};
+
+    //earth's gravitational parameter
+    const MU: f64 = 3.986e14;
+
+    #[test]
+    fn fit_reproduces_turn_angle() {
+        let (periapsis, v_infinity) = (7.0e6, 3000.0);
+        let turn = turn_angle(MU, periapsis, v_infinity);
+        let v_in = DVec2::X * v_infinity;
+        let v_out = DVec2::from_angle(turn).rotate(v_in);
+
+        let flyby = Flyby::fit(MU, 6.5e6, Entity::PLACEHOLDER, 0.0, v_in, v_out).expect("no flyby");
+        assert!((flyby.periapsis - periapsis).abs() < 1e-3, "{} vs {periapsis}", flyby.periapsis);
+        assert!((flyby.turn - turn).abs() < 1e-9);
+        assert!(flyby.burn.abs() < 1e-6, "{}", flyby.burn);
+
+        //too tight a turn for that close a pass
+        assert!(Flyby::fit(MU, 1.1 * periapsis, Entity::PLACEHOLDER, 0.0, v_in, v_out).is_none());
+    }
+
+    #[test]
+    fn fit_without_a_turn_is_no_flyby() {
+        let v_in = DVec2::new(3000.0, 1000.0);
+        assert!(Flyby::fit(MU, 6.5e6, Entity::PLACEHOLDER, 0.0, v_in, v_in).is_none());
+        assert!(Flyby::fit(MU, 6.5e6, Entity::PLACEHOLDER, 0.0, v_in, v_in * 1.5).is_none());
+    }
+
+    #[test]
+    fn search_goes_direct_past_a_body_too_light_to_help() {
+        let (sun, pebble, destination) = (Entity::from_raw(1), Entity::from_raw(2), Entity::from_raw(3));
+        let circle = |radius: f64| Orbit { parent: sun, ..Orbit::new(radius, radius, 0.0) };
+
+        let mut ephemeris = Ephemeris::default();
+        ephemeris.insert(sun, sols(1.0), 7.0e8, None);
+        ephemeris.insert(pebble, 1.0, 1.0, Some(circle(au(1.2))));
+        ephemeris.insert(destination, 6.0e24, 6.4e6, Some(circle(au(1.5))));
+
+        let origin = circle(au(1.0));
+        let period = origin.period(0.0, sols(1.0));
+        let request = TransferRequest { parent: sun, origin, origin_mass: 0.0, destination, departures: (0.0, period) };
+
+        let trajectories = search(&ephemeris, &request, &[pebble], 1, 8);
+        let best = trajectories.first().expect("no trajectory");
+        assert_eq!(best.bodies, vec![destination]);
+        assert!(best.flybys.is_empty());
+        assert!(best.delta_v().is_finite() && best.delta_v() > 0.0);
+    }
+}
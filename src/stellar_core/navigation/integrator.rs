@@ -0,0 +1,245 @@
+//Numerical integrators for bodies that are not on rails, i.e. the ship.
+//
+//These are unit agnostic. Time, position, velocity and acceleration only need to agree with each other.
+
+use std::ops::{Add, Mul};
+
+use bevy::math::DVec2;
+
+///Relative error per step the adaptive integrator aims for.
+pub const RK45_TOLERANCE: f64 = 1e-10;
+//stops the adaptive integrator from grinding to a halt right on top of a singularity
+const RK45_MAX_STEPS: u32 = 100_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Integrator {
+    ///Kick-drift-kick leapfrog (velocity Verlet). Symplectic, so energy errors stay bounded
+    ///over any number of orbits. Second order.
+    #[default]
+    Leapfrog,
+    ///Classic fourth order Runge-Kutta. Very accurate per step, but energy slowly drifts.
+    RK4,
+    ///Dormand-Prince 5(4) with adaptive step size, which shrinks its steps during close approaches.
+    RK45,
+}
+
+///Position and velocity of a point mass.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct State {
+    pub position: DVec2,
+    pub velocity: DVec2,
+}
+
+impl Add for State {
+    type Output = State;
+
+    fn add(self, other: State) -> State {
+        State { position: self.position + other.position, velocity: self.velocity + other.velocity }
+    }
+}
+
+impl Mul<f64> for State {
+    type Output = State;
+
+    fn mul(self, factor: f64) -> State {
+        State { position: self.position * factor, velocity: self.velocity * factor }
+    }
+}
+
+impl State {
+    pub fn new(position: DVec2, velocity: DVec2) -> Self {
+        State { position, velocity }
+    }
+
+    //time derivative of the state: velocity, and the acceleration at this position
+    fn derivative<F: Fn(DVec2, f64) -> DVec2>(&self, t: f64, acceleration: &F) -> State {
+        State { position: self.velocity, velocity: acceleration(self.position, t) }
+    }
+}
+
+impl Integrator {
+    ///The next integrator along, for cycling through them with a key.
+    pub fn next(&self) -> Self {
+        match self {
+            Integrator::Leapfrog => Integrator::RK4,
+            Integrator::RK4 => Integrator::RK45,
+            Integrator::RK45 => Integrator::Leapfrog,
+        }
+    }
+
+    ///Advances the state from time t by dt, taking steps no longer than max_step.
+    ///acceleration(position, time) gives the acceleration at a point.
+    pub fn advance<F: Fn(DVec2, f64) -> DVec2>(
+        &self, state: State, t: f64, dt: f64, max_step: f64, acceleration: F
+    ) -> State {
+        if dt <= 0.0 {
+            return state;
+        }
+
+        if *self == Integrator::RK45 {
+            return rk45(state, t, dt, max_step, RK45_TOLERANCE, &acceleration);
+        }
+
+        let steps = (dt / max_step).ceil().max(1.0) as u32;
+        let h = dt / steps as f64;
+
+        (0..steps).fold(state, |state, i| {
+            let t = t + i as f64 * h;
+            match self {
+                Integrator::Leapfrog => leapfrog(state, t, h, &acceleration),
+                _ => rk4(state, t, h, &acceleration),
+            }
+        })
+    }
+}
+
+fn leapfrog<F: Fn(DVec2, f64) -> DVec2>(state: State, t: f64, h: f64, acceleration: &F) -> State {
+    let half_kick = state.velocity + acceleration(state.position, t) * (h / 2.0);
+    let position = state.position + half_kick * h;
+    let velocity = half_kick + acceleration(position, t + h) * (h / 2.0);
+
+    State { position, velocity }
+}
+
+fn rk4<F: Fn(DVec2, f64) -> DVec2>(state: State, t: f64, h: f64, acceleration: &F) -> State {
+    let k1 = state.derivative(t, acceleration);
+    let k2 = (state + k1 * (h / 2.0)).derivative(t + h / 2.0, acceleration);
+    let k3 = (state + k2 * (h / 2.0)).derivative(t + h / 2.0, acceleration);
+    let k4 = (state + k3 * h).derivative(t + h, acceleration);
+
+    state + (k1 + k2 * 2.0 + k3 * 2.0 + k4) * (h / 6.0)
+}
+
+//Dormand-Prince 5(4). Each step gives a fifth order answer and a fourth order one,
+//and the difference between them is the error estimate that sets the next step size.
+fn rk45<F: Fn(DVec2, f64) -> DVec2>(
+    mut state: State, t: f64, dt: f64, max_step: f64, tolerance: f64, acceleration: &F
+) -> State {
+    let end = t + dt;
+    let mut t = t;
+    let mut h = max_step.min(dt);
+
+    for _ in 0..RK45_MAX_STEPS {
+        if t >= end {
+            break;
+        }
+        h = h.min(end - t);
+
+        let k1 = state.derivative(t, acceleration);
+        let k2 = (state + k1 * (h / 5.0)).derivative(t + h / 5.0, acceleration);
+        let k3 = (state + k1 * (h * 3.0 / 40.0) + k2 * (h * 9.0 / 40.0))
+            .derivative(t + h * 3.0 / 10.0, acceleration);
+        let k4 = (state + k1 * (h * 44.0 / 45.0) + k2 * (h * -56.0 / 15.0) + k3 * (h * 32.0 / 9.0))
+            .derivative(t + h * 4.0 / 5.0, acceleration);
+        let k5 = (state
+            + k1 * (h * 19372.0 / 6561.0) + k2 * (h * -25360.0 / 2187.0)
+            + k3 * (h * 64448.0 / 6561.0) + k4 * (h * -212.0 / 729.0))
+            .derivative(t + h * 8.0 / 9.0, acceleration);
+        let k6 = (state
+            + k1 * (h * 9017.0 / 3168.0) + k2 * (h * -355.0 / 33.0) + k3 * (h * 46732.0 / 5247.0)
+            + k4 * (h * 49.0 / 176.0) + k5 * (h * -5103.0 / 18656.0))
+            .derivative(t + h, acceleration);
+
+        let fifth = state
+            + (k1 * (35.0 / 384.0) + k3 * (500.0 / 1113.0) + k4 * (125.0 / 192.0)
+            + k5 * (-2187.0 / 6784.0) + k6 * (11.0 / 84.0)) * h;
+        let k7 = fifth.derivative(t + h, acceleration);
+        let fourth = state
+            + (k1 * (5179.0 / 57600.0) + k3 * (7571.0 / 16695.0) + k4 * (393.0 / 640.0)
+            + k5 * (-92097.0 / 339200.0) + k6 * (187.0 / 2100.0) + k7 * (1.0 / 40.0)) * h;
+
+        //error relative to the size of the state, so it works at any scale
+        let error = ((fifth.position - fourth.position).length() / fifth.position.length().max(f64::MIN_POSITIVE))
+            .max((fifth.velocity - fourth.velocity).length() / fifth.velocity.length().max(f64::MIN_POSITIVE))
+            / tolerance;
+
+        if error <= 1.0 {
+            state = fifth;
+            t += h;
+        }
+
+        //grow or shrink the step towards the tolerance, but not too suddenly
+        let factor = match error > 0.0 {
+            true => (0.9 * error.powf(-0.2)).clamp(0.2, 5.0),
+            false => 5.0,
+        };
+        h = (h * factor).min(max_step);
+    }
+
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::TAU;
+
+    use super::*;
+    use crate::stellar_core::solar_system::{Orbit, orbit::G};
+
+    //gravitational parameter of the test primary. the primary is fixed at the origin
+    const MU: f64 = 1.0;
+
+    fn gravity(position: DVec2, _t: f64) -> DVec2 {
+        -position * MU / position.length().powi(3)
+    }
+
+    fn energy(state: &State) -> f64 {
+        state.velocity.length_squared() / 2.0 - MU / state.position.length()
+    }
+
+    //flies an orbit of this eccentricity with a = 1 for this many periods, checking in 20 times an orbit.
+    //returns the worst relative energy error seen, and the final distance from the analytic position
+    fn fly(integrator: Integrator, eccentricity: f64, orbits: u32, steps_per_orbit: u32) -> (f64, f64) {
+        let orbit = Orbit::from_elements(1.0, eccentricity, 0.0, 0.0, 0.0, 0.0, 0.0);
+        let parent_mass = MU / G;
+
+        let (position, velocity) = orbit.state_at_time(0.0, 0.0, parent_mass);
+        let start = State::new(position.truncate(), velocity.truncate());
+
+        let period = orbit.period(0.0, parent_mass);
+        assert!((period - TAU).abs() < 1e-9);
+
+        let interval = period / 20.0;
+        let mut state = start;
+        let mut worst: f64 = 0.0;
+        for i in 0..orbits * 20 {
+            state = integrator.advance(state, i as f64 * interval, interval, period / steps_per_orbit as f64, gravity);
+            worst = worst.max(((energy(&state) - energy(&start)) / energy(&start)).abs());
+        }
+
+        let (analytic, _) = orbit.state_at_time(period * orbits as f64, 0.0, parent_mass);
+
+        (worst, (state.position - analytic.truncate()).length())
+    }
+
+    #[test]
+    fn leapfrog_energy_stays_bounded() {
+        let (drift, _) = fly(Integrator::Leapfrog, 0.0, 1000, 200);
+        assert!(drift < 1e-6, "circular drift {drift}");
+
+        //eccentric orbits swing further from the true energy around periapsis, but it never accumulates
+        let (short, _) = fly(Integrator::Leapfrog, 0.5, 10, 1000);
+        let (long, _) = fly(Integrator::Leapfrog, 0.5, 1000, 1000);
+        assert!(long < 1e-3, "eccentric drift {long}");
+        assert!(long < short * 1.5, "drift grew from {short} to {long}");
+    }
+
+    #[test]
+    fn rk4_tracks_analytic_orbit() {
+        let (drift, error) = fly(Integrator::RK4, 0.0, 100, 200);
+        assert!(drift < 1e-5, "circular drift {drift}");
+        assert!(error < 1e-3, "circular position error {error}");
+
+        let (drift, error) = fly(Integrator::RK4, 0.5, 100, 2000);
+        assert!(drift < 1e-6, "eccentric drift {drift}");
+        assert!(error < 1e-3, "eccentric position error {error}");
+    }
+
+    #[test]
+    fn rk45_handles_close_approaches() {
+        //periapsis at 0.05, where a fixed step would need to be tiny the whole way round
+        let (drift, error) = fly(Integrator::RK45, 0.95, 20, 50);
+        assert!(drift < 1e-6, "drift {drift}");
+        assert!(error < 1e-3, "position error {error}");
+    }
+}
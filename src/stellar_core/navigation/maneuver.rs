@@ -0,0 +1,65 @@
+//Maneuver nodes: burns planned for a point on the trajectory, given in the ship's local frame there.
+
+use bevy::math::DVec2;
+
+use super::integrator::State;
+use crate::stellar_core::solar_system::Ephemeris;
+
+///Acceleration (m/s²) of the main engine at full throttle, which sets how long a burn takes.
+pub const BURN_ACCELERATION: f64 = 10.0;
+
+///A planned burn, treated as instant when predicting. The ship flies in the plane of the system,
+///so there's no normal component to plan.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ManeuverNode {
+    ///Simulation time of the middle of the burn, in seconds.
+    pub time: f64,
+    ///Delta-v along the direction of travel relative to the dominant body, in m/s.
+    pub prograde: f64,
+    ///Delta-v in the plane at right angles to prograde, away from the dominant body, in m/s.
+    pub radial: f64,
+}
+
+impl ManeuverNode {
+    pub fn new(time: f64) -> Self {
+        ManeuverNode { time, ..Default::default() }
+    }
+
+    ///Total delta-v in m/s.
+    pub fn delta_v(&self) -> f64 {
+        (self.prograde.powi(2) + self.radial.powi(2)).sqrt()
+    }
+
+    ///Seconds the burn takes at full throttle.
+    pub fn burn_time(&self) -> f64 {
+        self.delta_v() / BURN_ACCELERATION
+    }
+
+    ///When to light the engines, so that the burn is centred on the node.
+    pub fn burn_start(&self) -> f64 {
+        self.time - self.burn_time() / 2.0
+    }
+
+    ///The burn as a change of velocity (m/s) in the world, for a ship in this state at time t.
+    pub fn world_delta_v(&self, ephemeris: &Ephemeris, state: State, t: f64) -> DVec2 {
+        let (prograde, radial) = local_frame(ephemeris, state, t);
+
+        prograde * self.prograde + radial * self.radial
+    }
+}
+
+///Unit prograde and radial directions in the world for a ship in this state at time t,
+///relative to the body whose sphere of influence it's in.
+pub fn local_frame(ephemeris: &Ephemeris, state: State, t: f64) -> (DVec2, DVec2) {
+    let (centre, velocity) = ephemeris.dominant_body(state.position, t)
+        .and_then(|body| ephemeris.state_at(body, t))
+        .unwrap_or_default();
+
+    let prograde = (state.velocity - velocity).normalize_or_zero();
+    let radial = match prograde.perp().dot(state.position - centre) < 0.0 {
+        true => -prograde.perp(),
+        false => prograde.perp(),
+    };
+
+    (prograde, radial)
+}
@@ -0,0 +1,121 @@
+//Events along a predicted trajectory: apsides, sphere of influence crossings, close approaches and impacts.
+
+use bevy::prelude::*;
+use bevy::math::DVec2;
+
+use crate::stellar_core::solar_system::Ephemeris;
+
+//distances have to vary by at least this fraction around an orbit before its apsides are worth marking,
+//otherwise a near circular orbit sprouts one every time a planet tugs on it
+const APSIS_THRESHOLD: f64 = 1e-3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarkerKind {
+    Periapsis,
+    Apoapsis,
+    ///Closest the ship gets to the selected target.
+    ClosestApproach,
+    SoiEntry,
+    SoiExit,
+    ///Where the trajectory runs into a body's surface.
+    Impact,
+}
+
+impl MarkerKind {
+    ///Short label for the HUD.
+    pub fn label(&self) -> &'static str {
+        match self {
+            MarkerKind::Periapsis => "PE",
+            MarkerKind::Apoapsis => "AP",
+            MarkerKind::ClosestApproach => "CA",
+            MarkerKind::SoiEntry => "SOI IN",
+            MarkerKind::SoiExit => "SOI OUT",
+            MarkerKind::Impact => "IMPACT",
+        }
+    }
+}
+
+///Something worth knowing about along a predicted trajectory.
+#[derive(Clone, Copy, Debug)]
+pub struct Marker {
+    pub kind: MarkerKind,
+    ///The body the event is about.
+    pub body: Entity,
+    ///Simulation time of the event, in seconds.
+    pub time: f64,
+    ///Where the ship will be, in meters.
+    pub position: DVec2,
+    ///Distance from the centre of the body, in meters.
+    pub distance: f64,
+}
+
+impl Marker {
+    ///Seconds from time t until the event. Negative once it has passed.
+    pub fn time_to(&self, t: f64) -> f64 {
+        self.time - t
+    }
+}
+
+///One point of a predicted trajectory: time (s), position (m), and the body whose sphere of influence it's in.
+pub type Sample = (f64, DVec2, Option<Entity>);
+
+///Finds apsides, sphere of influence crossings, and the closest approach to target along a trajectory.
+///Impacts are caught while predicting, since the trajectory ends there.
+pub fn find_markers(ephemeris: &Ephemeris, samples: &[Sample], target: Option<Entity>) -> Vec<Marker> {
+    let mut markers = Vec::new();
+
+    let distance = |body: Entity, (time, position, _): &Sample| {
+        ephemeris.position_at(body, *time).map_or(f64::INFINITY, |centre| centre.distance(*position))
+    };
+    let marker = |kind: MarkerKind, body: Entity, sample: &Sample| Marker {
+        kind, body, time: sample.0, position: sample.1, distance: distance(body, sample)
+    };
+
+    //apsides, one stretch around each dominant body at a time
+    for run in samples.chunk_by(|a, b| a.2 == b.2) {
+        let Some(body) = run[0].2 else { continue };
+
+        let distances: Vec<f64> = run.iter().map(|sample| distance(body, sample)).collect();
+        let (min, max) = distances.iter().fold((f64::INFINITY, 0.0_f64), |(min, max), &d| (min.min(d), max.max(d)));
+        if (max - min) < APSIS_THRESHOLD * max {
+            continue;
+        }
+
+        for i in 1..distances.len().saturating_sub(1) {
+            let (before, now, after) = (distances[i - 1], distances[i], distances[i + 1]);
+            if now < before && now <= after {
+                markers.push(marker(MarkerKind::Periapsis, body, &run[i]));
+            }
+            if now > before && now >= after {
+                markers.push(marker(MarkerKind::Apoapsis, body, &run[i]));
+            }
+        }
+    }
+
+    //sphere of influence crossings. moving straight from a moon to its planet's neighbour is an exit and an entry
+    for pair in samples.windows(2) {
+        let (Some(from), Some(to)) = (pair[0].2, pair[1].2) else { continue };
+        if from == to {
+            continue;
+        }
+
+        if !ephemeris.is_ancestor(from, to) {
+            markers.push(marker(MarkerKind::SoiExit, from, &pair[1]));
+        }
+        if !ephemeris.is_ancestor(to, from) {
+            markers.push(marker(MarkerKind::SoiEntry, to, &pair[1]));
+        }
+    }
+
+    //closest approach, as long as the ship is heading towards the target at all
+    if let Some(target) = target {
+        let closest = samples.iter().enumerate()
+            .min_by(|(_, a), (_, b)| distance(target, a).total_cmp(&distance(target, b)));
+        if let Some((_, sample)) = closest.filter(|(i, _)| *i > 0) {
+            markers.push(marker(MarkerKind::ClosestApproach, target, sample));
+        }
+    }
+
+    markers.sort_by(|a, b| a.time.total_cmp(&b.time));
+    markers
+}
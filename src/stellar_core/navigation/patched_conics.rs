@@ -0,0 +1,143 @@
+//Patched conics: the ship only feels the body whose sphere of influence it's in, so between
+//boundaries its path is an exact Kepler orbit that can be evaluated at any time, at any warp.
+
+use bevy::prelude::*;
+
+use super::integrator::State;
+use crate::stellar_core::solar_system::{Ephemeris, Orbit};
+
+///Most sphere of influence boundaries crossed in one step, in case the ship skims along one.
+const MAX_CROSSINGS: u32 = 8;
+///How closely the time of a boundary crossing is pinned down, in seconds.
+const CROSSING_TOLERANCE: f64 = 1e-3;
+///Cap on the points a step is checked at for boundaries, so a crawl past a tiny moon can't stall the frame.
+const MAX_SAMPLES: f64 = 4096.0;
+
+///Fired when a ship moves into the sphere of influence of a different body.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SoiChange {
+    pub ship: Entity,
+    ///The body the ship was orbiting, or None if it wasn't orbiting anything yet.
+    pub from: Option<Entity>,
+    pub to: Entity,
+    ///Simulation time of the crossing, in seconds.
+    pub time: f64,
+}
+
+///One piece of a patched conic trajectory: a Kepler orbit around the dominant body.
+#[derive(Clone, Debug)]
+pub struct Patch {
+    pub reference: Entity,
+    ///Relative to the reference body. Its parent is left as a placeholder, the ship isn't in the hierarchy.
+    pub orbit: Orbit,
+}
+
+impl Patch {
+    ///Fits a patch to a position (m) and velocity (m/s) at time t, around whichever body dominates there.
+    pub fn fit(ephemeris: &Ephemeris, state: State, t: f64) -> Option<Patch> {
+        let reference = ephemeris.dominant_body(state.position, t)?;
+        Patch::fit_around(ephemeris, reference, state, t)
+    }
+
+    ///Fits a patch to a position (m) and velocity (m/s) at time t, around this body.
+    pub fn fit_around(ephemeris: &Ephemeris, reference: Entity, state: State, t: f64) -> Option<Patch> {
+        let (position, velocity) = ephemeris.state_at(reference, t)?;
+        let orbit = Orbit::from_state_vectors(
+            (state.position - position).extend(0.0),
+            (state.velocity - velocity).extend(0.0),
+            0.0, ephemeris.mass(reference), t
+        );
+
+        Some(Patch { reference, orbit })
+    }
+
+    ///Position (m) and velocity (m/s) in the world at time t, assuming the ship stays in this patch.
+    pub fn state_at(&self, ephemeris: &Ephemeris, t: f64) -> State {
+        let (position, velocity) = ephemeris.state_at(self.reference, t).unwrap_or_default();
+        let (relative_position, relative_velocity) = self.orbit.state_at_time(t, 0.0, ephemeris.mass(self.reference));
+
+        State::new(position + relative_position.truncate(), velocity + relative_velocity.truncate())
+    }
+
+    ///Coasts from t to t + dt, switching to a new patch at every sphere of influence boundary on the way.
+    ///Returns the crossings as (from, to, time).
+    ///The step is checked at points close enough together that no sphere fits between two of them, so
+    ///high warp doesn't skip a moon the ship passes straight through.
+    pub fn coast(&mut self, ephemeris: &Ephemeris, t: f64, dt: f64) -> Vec<(Entity, Entity, f64)> {
+        let end = t + dt;
+        let mut start = t;
+        let mut crossings = Vec::new();
+
+        for _ in 0..MAX_CROSSINGS {
+            let dominant = |patch: &Patch, t: f64| ephemeris.dominant_body(patch.state_at(ephemeris, t).position, t);
+
+            let Some((mut before, mut after)) = self.first_departure(ephemeris, start, end) else { break };
+
+            //bisect for the moment the ship crosses over
+            while after - before > CROSSING_TOLERANCE {
+                let middle = (before + after) / 2.0;
+                match dominant(self, middle) == Some(self.reference) {
+                    true => before = middle,
+                    false => after = middle,
+                }
+            }
+
+            let Some(next) = Patch::fit(ephemeris, self.state_at(ephemeris, after), after) else { break };
+            crossings.push((self.reference, next.reference, after));
+
+            *self = next;
+            start = after;
+        }
+
+        crossings
+    }
+
+    //the first stretch of (start, end], as (still in, out), where the ship has left this patch's sphere of influence.
+    //between samples it checks the closest approach to each sphere, so one crossed between two of them still counts
+    fn first_departure(&self, ephemeris: &Ephemeris, start: f64, end: f64) -> Option<(f64, f64)> {
+        let outside = |t: f64| ephemeris.dominant_body(self.state_at(ephemeris, t).position, t)
+            .is_some_and(|body| body != self.reference);
+
+        //the shortest time any sphere takes to cross at the speed the ship passes it now
+        let here = self.state_at(ephemeris, start);
+        let spacing = ephemeris.iter()
+            .filter(|(_, body)| body.soi.is_finite())
+            .filter_map(|(entity, body)| {
+                let (_, velocity) = ephemeris.state_at(entity, start)?;
+                Some(body.soi / here.velocity.distance(velocity))
+            })
+            .fold(end - start, f64::min);
+        let samples = ((end - start) / spacing).ceil().clamp(1.0, MAX_SAMPLES) as u32;
+
+        let mut previous = start;
+        for i in 1..=samples {
+            let next = start + (end - start) * i as f64 / samples as f64;
+            if outside(next) {
+                return Some((previous, next));
+            }
+
+            let (from, to) = (self.state_at(ephemeris, previous), self.state_at(ephemeris, next));
+            for (entity, body) in ephemeris.iter().filter(|(entity, body)| body.soi.is_finite() && *entity != self.reference) {
+                let (Some(a), Some(b)) = (ephemeris.position_at(entity, previous), ephemeris.position_at(entity, next))
+                    else { continue };
+
+                //closest approach along the chord, relative to the body
+                let (a, b) = (from.position - a, to.position - b);
+                let along = (-a.dot(b - a) / (b - a).length_squared().max(f64::MIN_POSITIVE)).clamp(0.0, 1.0);
+                if a.lerp(b, along).length() >= body.soi {
+                    continue;
+                }
+
+                let closest = previous + (next - previous) * along;
+                if outside(closest) {
+                    return Some((previous, closest));
+                }
+            }
+
+            previous = next;
+        }
+
+        None
+    }
+}
+
@@ -0,0 +1,121 @@
+//Porkchop plots: the delta-v of a Lambert transfer for every pairing of departure time and flight time.
+
+use bevy::prelude::*;
+use bevy::math::DVec2;
+use image::{Rgba, RgbaImage};
+
+use super::transfer::{lambert, TransferRequest};
+use crate::stellar_core::solar_system::{Ephemeris, orbit::G};
+
+///Delta-v of Lambert transfers from an orbit to a body orbiting the same parent, over a grid of
+///departure times and flight times.
+#[derive(Clone, Debug)]
+pub struct PorkchopGrid {
+    pub destination: Entity,
+    ///Departure times down the grid, in seconds.
+    pub departures: Vec<f64>,
+    ///Flight times across the grid, in seconds.
+    pub flight_times: Vec<f64>,
+    ///Delta-v (m/s) to leave the origin's orbit and match the destination's, one row per departure.
+    ///Infinite where there's no transfer.
+    pub delta_v: Vec<f64>,
+}
+
+impl PorkchopGrid {
+    ///Works out the grid for the requested transfer, with departure times (s) and flight times (s) running
+    ///between the given bounds in this many steps each. None unless the destination orbits the parent too.
+    pub fn new(
+        ephemeris: &Ephemeris, request: &TransferRequest, flight_times: (f64, f64), resolution: usize
+    ) -> Option<PorkchopGrid> {
+        let TransferRequest { parent, ref origin, origin_mass, destination, departures } = *request;
+        if ephemeris.parent(destination)? != parent || resolution < 2 {
+            return None;
+        }
+        let parent_mass = ephemeris.mass(parent);
+        let mu = G * parent_mass;
+
+        let steps = |(first, last): (f64, f64)| -> Vec<f64> {
+            (0..resolution).map(|i| first + (last - first) * i as f64 / (resolution - 1) as f64).collect()
+        };
+        let departures = steps(departures);
+        let flight_times = steps(flight_times);
+
+        //relative to the parent, which the transfer orbits
+        let departure_state = |t: f64| {
+            let (position, velocity) = origin.state_at_time(t, origin_mass, parent_mass);
+            Some((position.truncate(), velocity.truncate()))
+        };
+        let arrival_state = |t: f64| -> Option<(DVec2, DVec2)> {
+            let (position, velocity) = ephemeris.state_at(destination, t)?;
+            let (centre, centre_velocity) = ephemeris.state_at(parent, t)?;
+            Some((position - centre, velocity - centre_velocity))
+        };
+
+        let delta_v = departures.iter()
+            .flat_map(|&departure| flight_times.iter().map(move |&flight_time| (departure, flight_time)))
+            .map(|(departure, flight_time)| {
+                let (Some((r1, v1)), Some((r2, v2))) = (departure_state(departure), arrival_state(departure + flight_time))
+                    else { return f64::INFINITY };
+
+                lambert(mu, r1, r2, flight_time)
+                    .map_or(f64::INFINITY, |(leave, arrive)| (leave - v1).length() + (v2 - arrive).length())
+            })
+            .collect();
+
+        Some(PorkchopGrid { destination, departures, flight_times, delta_v })
+    }
+
+    pub fn delta_v_at(&self, departure: usize, flight_time: usize) -> f64 {
+        self.delta_v[departure * self.flight_times.len() + flight_time]
+    }
+
+    ///The cheapest transfer on the grid, as (departure, flight time, delta-v).
+    pub fn best(&self) -> Option<(f64, f64, f64)> {
+        let (i, delta_v) = self.delta_v.iter().enumerate()
+            .filter(|(_, dv)| dv.is_finite())
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        let columns = self.flight_times.len();
+
+        Some((self.departures[i / columns], self.flight_times[i % columns], *delta_v))
+    }
+
+    ///The grid as CSV: a header of flight times, then one row per departure time, all in seconds and m/s.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("departure_s\\flight_s");
+        for flight_time in &self.flight_times {
+            csv += &format!(",{flight_time:.0}");
+        }
+
+        for (i, departure) in self.departures.iter().enumerate() {
+            csv += &format!("\n{departure:.0}");
+            for j in 0..self.flight_times.len() {
+                let delta_v = self.delta_v_at(i, j);
+                match delta_v.is_finite() {
+                    true => csv += &format!(",{delta_v:.1}"),
+                    false => csv += ",",
+                }
+            }
+        }
+
+        csv + "\n"
+    }
+
+    ///The grid as a picture: departure time left to right, flight time bottom to top, and delta-v from
+    ///blue at the cheapest through to red at four times that. Missing transfers are black.
+    pub fn to_image(&self) -> RgbaImage {
+        let (width, height) = (self.departures.len() as u32, self.flight_times.len() as u32);
+        let cheapest = self.best().map_or(1.0, |(_, _, delta_v)| delta_v);
+
+        RgbaImage::from_fn(width, height, |x, y| {
+            let delta_v = self.delta_v_at(x as usize, (height - 1 - y) as usize);
+            if !delta_v.is_finite() {
+                return Rgba([0, 0, 0, 255]);
+            }
+
+            //0 at the cheapest, 1 at four times that and beyond
+            let t = ((delta_v / cheapest - 1.0) / 3.0).clamp(0.0, 1.0) as f32;
+            let color = Color::hsl(240.0 * (1.0 - t), 1.0, 0.5).to_srgba();
+            Rgba([(color.red * 255.0) as u8, (color.green * 255.0) as u8, (color.blue * 255.0) as u8, 255])
+        })
+    }
+}
@@ -0,0 +1,260 @@
+//Trajectory prediction. Bodies are put where their orbits will have taken them at each future time,
+//so encounters come out right, and steps shrink close in to whatever the ship is orbiting.
+
+use std::f64::consts::TAU;
+
+use bevy::prelude::*;
+use bevy::math::DVec2;
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
+
+use super::{ephemeris_acceleration, Integrator, Patch, integrator::State};
+use super::collision::{find_contact, Contact};
+use super::drag::{air_at, apply_drag, Airframe};
+use super::radiation::radiation_acceleration;
+use super::markers::{find_markers, Marker, MarkerKind, Sample};
+use super::maneuver::ManeuverNode;
+use crate::stellar_core::solar_system::{Ephemeris, Orbit, orbit::G};
+
+//shortest step the predictor takes, in seconds, so it can't stall right on top of a body
+const MIN_STEP: f64 = 1.0;
+//steps per scale height of atmosphere crossed, so a pass through the air isn't stepped over
+const STEPS_PER_SCALE_HEIGHT: f64 = 4.0;
+
+///How far ahead to predict.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Horizon {
+    ///A fixed length of time, in seconds.
+    Duration(f64),
+    ///This many periods of the ship's current orbit. Escape trajectories run until they leave the sphere of influence.
+    Orbits(f64),
+    ///Until the ship moves into another body's sphere of influence.
+    SoiChange,
+}
+
+///Settings for the trajectory predictor.
+#[derive(Resource, Clone, Debug)]
+pub struct PredictionConfig {
+    pub horizon: Horizon,
+    ///Cap on points, which is what actually bounds the work on long or open-ended horizons.
+    pub max_points: usize,
+    ///Points per orbit at the ship's current distance from the dominant body. Sets the step size.
+    pub points_per_orbit: f64,
+    ///Softening length for gravity in meters, see navigation::ephemeris_acceleration.
+    pub softening: f64,
+    ///Body to mark the closest approach to.
+    pub target: Option<Entity>,
+}
+
+impl Default for PredictionConfig {
+    fn default() -> Self {
+        PredictionConfig {
+            horizon: Horizon::Orbits(1.0),
+            max_points: 2000,
+            points_per_orbit: 100.0,
+            softening: super::NO_SOFTENING,
+            target: None,
+        }
+    }
+}
+
+///A predicted trajectory.
+#[derive(Clone, Debug, Default)]
+pub struct Prediction {
+    ///Positions in meters, starting where the ship was.
+    pub points: Vec<DVec2>,
+    ///Simulation time of each point, in seconds.
+    pub times: Vec<f64>,
+    ///The first sphere of influence the ship moves into, and when.
+    pub soi_change: Option<(Entity, f64)>,
+    ///Events along the way, in time order.
+    pub markers: Vec<Marker>,
+    ///Time (s) of each maneuver node that was reached, with the position (m) and velocity (m/s) just before it.
+    ///Nodes beyond the horizon or after an impact are left out.
+    pub burns: Vec<(f64, State)>,
+    ///Where the trajectory ends on a body's surface, if it does.
+    pub impact: Option<Contact>,
+}
+
+impl Prediction {
+    ///Predicts from a position (m) and velocity (m/s) at time t. With a patch the ship coasts along
+    ///patched conics, otherwise it's integrated against every body.
+    ///Maneuver nodes are burned instantly as they're reached, and the horizon starts over from each one.
+    ///Atmospheres drag on the airframe along the way, and starlight pushes on it.
+    pub fn new(
+        ephemeris: &Ephemeris, start: State, t: f64, patch: Option<Patch>,
+        maneuvers: &[ManeuverNode], airframe: &Airframe, config: &PredictionConfig
+    ) -> Prediction {
+        let mut prediction = Prediction::default();
+        let Some(reference) = ephemeris.dominant_body(start.position, t) else { return prediction };
+
+        let (duration, mut stop_at_soi) = horizon(ephemeris, reference, start, t, config);
+
+        let mut patch = patch;
+        let mut state = start;
+        let mut time = t;
+        let mut end = t + duration;
+        let mut samples: Vec<Sample> = vec![(t, start.position, Some(reference))];
+        prediction.points.push(start.position);
+        prediction.times.push(t);
+        let mut maneuvers = maneuvers.iter().filter(|node| node.time > t).peekable();
+
+        while prediction.points.len() < config.max_points && time < end {
+            let mut step = local_step(ephemeris, state, time, config.points_per_orbit).min(end - time);
+            //land exactly on the next node
+            let node = maneuvers.next_if(|node| node.time - time <= step);
+            if let Some(node) = node {
+                step = node.time - time;
+            }
+
+            let before = state;
+            state = match &mut patch {
+                Some(patch) => {
+                    patch.coast(ephemeris, time, step);
+                    patch.state_at(ephemeris, time + step)
+                },
+                None => Integrator::RK45.advance(state, time, step, step, |position, t| {
+                    ephemeris_acceleration(ephemeris, position, t, config.softening)
+                }),
+            };
+            time += step;
+
+            //drag and starlight are kicked in after each step, like a burn.
+            //conics leave out small pushes like the light on the hull, but not a deployed sail
+            let mut kicked = apply_drag(ephemeris, airframe, state, time, step);
+            if patch.is_none() || airframe.sail.is_some() {
+                kicked.velocity += radiation_acceleration(ephemeris, airframe, kicked, time) * step;
+            }
+            if kicked.velocity != state.velocity {
+                state = kicked;
+                if patch.is_some() {
+                    patch = Patch::fit(ephemeris, state, time);
+                }
+            }
+
+            if let Some(node) = node {
+                prediction.burns.push((node.time, state));
+                state.velocity += node.world_delta_v(ephemeris, state, time);
+                if patch.is_some() {
+                    patch = Patch::fit(ephemeris, state, time);
+                }
+
+                //show the new orbit in full
+                if let Some(reference) = ephemeris.dominant_body(state.position, time) {
+                    let (duration, stop) = horizon(ephemeris, reference, state, time, config);
+                    end = time + duration;
+                    stop_at_soi = stop;
+                }
+            }
+
+            prediction.points.push(state.position);
+            prediction.times.push(time);
+
+            let dominant = ephemeris.dominant_body(state.position, time);
+            samples.push((time, state.position, dominant));
+
+            //the trajectory ends at the surface of whatever it runs into
+            if let Some(contact) = find_contact(ephemeris, before, state, time - step, step) {
+                prediction.points.pop();
+                prediction.times.pop();
+                prediction.points.push(contact.position);
+                prediction.times.push(contact.time);
+                prediction.impact = Some(contact);
+                break;
+            }
+
+            if prediction.soi_change.is_none() && dominant.is_some_and(|body| body != reference) {
+                prediction.soi_change = dominant.map(|body| (body, time));
+                if stop_at_soi {
+                    break;
+                }
+            }
+        }
+
+        prediction.markers = find_markers(ephemeris, &samples, config.target);
+        prediction.markers.extend(prediction.impact.map(|contact| Marker {
+            kind: MarkerKind::Impact,
+            body: contact.body,
+            time: contact.time,
+            position: contact.position,
+            distance: ephemeris.body(contact.body).map_or(0.0, |body| body.radius),
+        }));
+
+        prediction
+    }
+
+    ///How much of the prediction is left after time t, from 0 to 1.
+    pub fn remaining(&self, t: f64) -> f64 {
+        let (Some(&start), Some(&end)) = (self.times.first(), self.times.last()) else { return 0.0 };
+        match end > start {
+            true => ((end - t) / (end - start)).clamp(0.0, 1.0),
+            false => 0.0,
+        }
+    }
+
+    ///Predicted position (m) at time t, between the points either side. None outside the prediction.
+    pub fn position_at(&self, t: f64) -> Option<DVec2> {
+        let next = self.times.iter().position(|&time| time >= t)?;
+        if next == 0 {
+            return (self.times[0] == t).then_some(self.points[0]);
+        }
+
+        let (start, end) = (self.times[next - 1], self.times[next]);
+        Some(self.points[next - 1].lerp(self.points[next], (t - start) / (end - start)))
+    }
+}
+
+///A prediction being worked out in the background.
+#[derive(Component)]
+pub struct PredictionTask(Task<Prediction>);
+
+impl PredictionTask {
+    ///Starts predicting on the async compute pool, from a snapshot of the ephemeris.
+    pub fn spawn(
+        ephemeris: Ephemeris, start: State, t: f64, patch: Option<Patch>,
+        maneuvers: Vec<ManeuverNode>, airframe: Airframe, config: PredictionConfig
+    ) -> PredictionTask {
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            Prediction::new(&ephemeris, start, t, patch, &maneuvers, &airframe, &config)
+        });
+
+        PredictionTask(task)
+    }
+
+    ///The prediction, if it's done.
+    pub fn poll(&mut self) -> Option<Prediction> {
+        block_on(poll_once(&mut self.0))
+    }
+}
+
+//how long to predict for from this state, and whether to stop at the first sphere of influence change
+fn horizon(ephemeris: &Ephemeris, reference: Entity, state: State, t: f64, config: &PredictionConfig) -> (f64, bool) {
+    let osculating = osculating_orbit(ephemeris, reference, state, t);
+
+    match config.horizon {
+        Horizon::Duration(duration) => (duration, false),
+        Horizon::Orbits(n) if osculating.is_bound() =>
+            (n * osculating.period(0.0, ephemeris.mass(reference)), false),
+        _ => (f64::INFINITY, true),
+    }
+}
+
+//the ship's orbit around this body, as if nothing else pulled on it
+fn osculating_orbit(ephemeris: &Ephemeris, reference: Entity, state: State, t: f64) -> Orbit {
+    Patch::fit_around(ephemeris, reference, state, t)
+        .map(|patch| patch.orbit)
+        .unwrap_or_default()
+}
+
+//a step that's a fixed fraction of a circular orbit at the ship's distance from the dominant body,
+//so the steps shorten on the way in and stretch on the way out. they shorten again in the air
+fn local_step(ephemeris: &Ephemeris, state: State, t: f64, points_per_orbit: f64) -> f64 {
+    let Some(body) = ephemeris.dominant_body(state.position, t) else { return MIN_STEP };
+    let Some(centre) = ephemeris.position_at(body, t) else { return MIN_STEP };
+
+    let r = state.position.distance(centre);
+    let period = TAU * (r.powi(3) / (G * ephemeris.mass(body))).sqrt();
+    let air_step = air_at(ephemeris, state, t)
+        .map_or(f64::INFINITY, |air| air.scale_height / air.velocity.length() / STEPS_PER_SCALE_HEIGHT);
+
+    (period / points_per_orbit).min(air_step).max(MIN_STEP)
+}
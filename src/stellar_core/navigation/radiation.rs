@@ -0,0 +1,85 @@
+//Radiation pressure: starlight pushing on the hull and, when it's out, the solar sail.
+
+use std::f64::consts::PI;
+
+use bevy::math::DVec2;
+
+use super::drag::Airframe;
+use super::integrator::State;
+use crate::stellar_core::solar_system::Ephemeris;
+use crate::stellar_utils::unit_conversion::SPEED_OF_LIGHT;
+
+///A flat sail that can be turned against the light to steer the push it gets.
+#[derive(Clone, Copy, Debug)]
+pub struct SolarSail {
+    ///Area in m².
+    pub area: f64,
+    ///Fraction of the light reflected rather than absorbed.
+    pub reflectivity: f64,
+    ///Angle of the sail to the starlight, in radians. 0 faces the star square on. Positive turns the push
+    ///towards the direction of travel around the star, to spiral outwards, negative against it, to spiral in.
+    pub angle: f64,
+}
+
+impl Default for SolarSail {
+    //a hundred meters square
+    fn default() -> Self {
+        SolarSail { area: 10_000.0, reflectivity: 0.9, angle: 0.0 }
+    }
+}
+
+impl SolarSail {
+    ///Direction the sail faces, away from the star. away is the unit vector from the star to the ship,
+    ///and velocity is the ship's velocity relative to the star, which says which way is forwards.
+    pub fn normal(&self, away: DVec2, velocity: DVec2) -> DVec2 {
+        let forwards = match away.perp_dot(velocity) < 0.0 {
+            true => -1.0,
+            false => 1.0,
+        };
+
+        DVec2::from_angle(self.angle * forwards).rotate(away)
+    }
+
+    ///Force (N) on the sail from light at this pressure (Pa). Reflected light pushes straight
+    ///out of the sail, absorbed light along the beam, and a sail edge on to the star catches nothing.
+    pub fn force(&self, pressure: f64, away: DVec2, velocity: DVec2) -> DVec2 {
+        let normal = self.normal(away, velocity);
+        let cos = normal.dot(away).max(0.0);
+
+        pressure * self.area * (2.0 * self.reflectivity * cos * cos * normal + (1.0 - self.reflectivity) * cos * away)
+    }
+}
+
+///Acceleration (m/s²) starlight gives a ship in this state at time t, summed over every star.
+///The hull takes it square on; the sail takes it at whatever angle it's set to.
+pub fn radiation_acceleration(ephemeris: &Ephemeris, airframe: &Airframe, state: State, t: f64) -> DVec2 {
+    let force: DVec2 = sunlight(ephemeris, state, t)
+        .map(|(pressure, away, velocity)| {
+            let hull = pressure * airframe.area * (1.0 + airframe.reflectivity) * away;
+            let sail = airframe.sail.map_or(DVec2::ZERO, |sail| sail.force(pressure, away, velocity));
+            hull + sail
+        })
+        .sum();
+
+    force / airframe.mass
+}
+
+///Which way the sail faces for a ship in this state at time t, going by the brightest star in its sky.
+pub fn sail_normal(ephemeris: &Ephemeris, sail: &SolarSail, state: State, t: f64) -> Option<DVec2> {
+    sunlight(ephemeris, state, t)
+        .max_by(|(a, _, _), (b, _, _)| a.total_cmp(b))
+        .map(|(_, away, velocity)| sail.normal(away, velocity))
+}
+
+//radiation pressure (Pa), the unit vector away from the star, and the ship's velocity relative to it, for each star
+fn sunlight(ephemeris: &Ephemeris, state: State, t: f64) -> impl Iterator<Item = (f64, DVec2, DVec2)> + '_ {
+    ephemeris.iter()
+        .filter(|(_, body)| body.luminosity > 0.0)
+        .filter_map(move |(entity, body)| {
+            let (centre, velocity) = ephemeris.state_at(entity, t)?;
+            let offset = state.position - centre;
+            let pressure = body.luminosity / (4.0 * PI * offset.length_squared() * SPEED_OF_LIGHT);
+
+            pressure.is_finite().then(|| (pressure, offset.normalize(), state.velocity - velocity))
+        })
+}
@@ -0,0 +1,238 @@
+//Transfers between two circular orbits around the same body: Hohmann and bi-elliptic estimates,
+//launch windows, and a Lambert solver for the real thing.
+
+use std::f64::consts::{PI, TAU};
+
+use bevy::prelude::Entity;
+use bevy::math::DVec2;
+
+use crate::stellar_core::solar_system::Orbit;
+
+///Bisection steps for the Lambert solver. Each one halves the bracket, so this is plenty for f64.
+const LAMBERT_ITERATIONS: u32 = 100;
+
+///Burns and flight time for a transfer between two orbits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transfer {
+    ///Delta-v (m/s) of each burn, in order.
+    pub burns: [f64; 3],
+    ///Time (s) from the first burn to the last.
+    pub flight_time: f64,
+}
+
+impl Transfer {
+    ///Delta-v of all the burns together, in m/s.
+    pub fn delta_v(&self) -> f64 {
+        self.burns.iter().sum()
+    }
+}
+
+///Where a transfer starts and ends: from the origin orbit (around parent, carrying origin_mass kg)
+///to a destination orbiting the same parent, leaving between the two departure times (s).
+#[derive(Clone, Debug)]
+pub struct TransferRequest {
+    pub parent: Entity,
+    pub origin: Orbit,
+    pub origin_mass: f64,
+    pub destination: Entity,
+    pub departures: (f64, f64),
+}
+
+///Hohmann transfer from a circular orbit of radius r1 (m) to one of radius r2 (m),
+///around a body with gravitational parameter mu (m³/s²). The last burn is left at zero.
+pub fn hohmann(mu: f64, r1: f64, r2: f64) -> Transfer {
+    let a = (r1 + r2) / 2.0;
+
+    let departure = ((mu / r1).sqrt() * ((r2 / a).sqrt() - 1.0)).abs();
+    let arrival = ((mu / r2).sqrt() * (1.0 - (r1 / a).sqrt())).abs();
+
+    Transfer { burns: [departure, arrival, 0.0], flight_time: PI * (a.powi(3) / mu).sqrt() }
+}
+
+///Bi-elliptic transfer from radius r1 to r2 (m) by way of an apoapsis at rb (m), around a body with
+///gravitational parameter mu (m³/s²). It beats Hohmann when r2 is more than about 12 times r1.
+pub fn bi_elliptic(mu: f64, r1: f64, r2: f64, rb: f64) -> Transfer {
+    let (a1, a2) = ((r1 + rb) / 2.0, (r2 + rb) / 2.0);
+    let speed = |r: f64, a: f64| (mu * (2.0 / r - 1.0 / a)).sqrt();
+
+    let first = (speed(r1, a1) - (mu / r1).sqrt()).abs();
+    let second = (speed(rb, a2) - speed(rb, a1)).abs();
+    let third = (speed(r2, a2) - (mu / r2).sqrt()).abs();
+
+    Transfer {
+        burns: [first, second, third],
+        flight_time: PI * ((a1.powi(3) / mu).sqrt() + (a2.powi(3) / mu).sqrt()),
+    }
+}
+
+///How far ahead (rad) the destination has to be of the origin at departure for a Hohmann transfer
+///from radius r1 to r2 (m) to meet it. Negative when it has to be behind.
+pub fn phase_angle(r1: f64, r2: f64) -> f64 {
+    PI * (1.0 - ((r1 + r2) / (2.0 * r2)).powf(1.5))
+}
+
+///Seconds until the destination is phase (rad) ahead of the origin, when it's now current (rad) ahead,
+///with the origin and destination going round at mean motions n1 and n2 (rad/s).
+///None if they go round together and the angle never changes.
+pub fn next_window(current: f64, phase: f64, n1: f64, n2: f64) -> Option<f64> {
+    let rate = n2 - n1;
+    if rate == 0.0 {
+        return None;
+    }
+
+    let synodic_period = TAU / rate.abs();
+    Some(((phase - current) / rate).rem_euclid(synodic_period))
+}
+
+///Solves Lambert's problem: the velocities (m/s) at r1 and r2 (m, from the central body) for an anticlockwise
+///transfer taking flight_time (s), around a body with gravitational parameter mu (m³/s²).
+///Universal variables, less than one revolution. None if no such transfer exists.
+pub fn lambert(mu: f64, r1: DVec2, r2: DVec2, flight_time: f64) -> Option<(DVec2, DVec2)> {
+    let (length1, length2) = (r1.length(), r2.length());
+    if flight_time <= 0.0 || length1 == 0.0 || length2 == 0.0 {
+        return None;
+    }
+
+    //the angle swept going anticlockwise from r1 to r2
+    let cos = (r1.dot(r2) / (length1 * length2)).clamp(-1.0, 1.0);
+    let angle = match r1.perp_dot(r2) < 0.0 {
+        true => TAU - cos.acos(),
+        false => cos.acos(),
+    };
+    if 1.0 - angle.cos() < f64::EPSILON {
+        return None;
+    }
+    let a = angle.sin() * (length1 * length2 / (1.0 - angle.cos())).sqrt();
+
+    let y = |z: f64| length1 + length2 + a * (z * stumpff_s(z) - 1.0) / stumpff_c(z).sqrt();
+    let time = |z: f64| {
+        let y = y(z);
+        ((y / stumpff_c(z)).powf(1.5) * stumpff_s(z) + a * y.sqrt()) / mu.sqrt()
+    };
+
+    //flight time rises with z. bracket it between where y turns positive and a full revolution
+    let mut high = 4.0 * PI * PI - 1e-9;
+    let mut low = -4.0 * PI * PI;
+    if y(low) < 0.0 {
+        let mut positive = high;
+        for _ in 0..LAMBERT_ITERATIONS {
+            let middle = (low + positive) / 2.0;
+            match y(middle) < 0.0 {
+                true => low = middle,
+                false => positive = middle,
+            }
+        }
+        low = positive;
+    }
+    if !(time(low) <= flight_time && flight_time <= time(high)) {
+        return None;
+    }
+
+    for _ in 0..LAMBERT_ITERATIONS {
+        let middle = (low + high) / 2.0;
+        match time(middle) < flight_time {
+            true => low = middle,
+            false => high = middle,
+        }
+    }
+
+    let y = y((low + high) / 2.0);
+    let f = 1.0 - y / length1;
+    let g = a * (y / mu).sqrt();
+    let g_dot = 1.0 - y / length2;
+
+    Some(((r2 - f * r1) / g, (g_dot * r2 - r1) / g))
+}
+
+//stumpff functions, with their series near zero where the closed forms lose precision.
+//1 - cos is taken as 2 sin² of the half angle, which stays accurate up to a full revolution where cos rounds to 1
+fn stumpff_c(z: f64) -> f64 {
+    match z {
+        z if z > 1e-6 => 2.0 * (z.sqrt() / 2.0).sin().powi(2) / z,
+        z if z < -1e-6 => ((-z).sqrt().cosh() - 1.0) / -z,
+        z => 0.5 - z / 24.0,
+    }
+}
+
+fn stumpff_s(z: f64) -> f64 {
+    match z {
+        z if z > 1e-6 => (z.sqrt() - z.sqrt().sin()) / z.powf(1.5),
+        z if z < -1e-6 => ((-z).sqrt().sinh() - (-z).sqrt()) / (-z).powf(1.5),
+        z => 1.0 / 6.0 - z / 120.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stellar_core::solar_system::orbit::G;
+
+    //gravitational parameter of the test primary
+    const MU: f64 = 1.0;
+
+    //position and velocity along an orbit in the plane, t seconds after periapsis
+    fn state(orbit: &Orbit, t: f64) -> (DVec2, DVec2) {
+        let (position, velocity) = orbit.state_at_time(t, 0.0, MU / G);
+        (position.truncate(), velocity.truncate())
+    }
+
+    fn assert_close(a: DVec2, b: DVec2, tolerance: f64) {
+        assert!((a - b).length() < tolerance, "{a} vs {b}");
+    }
+
+    #[test]
+    fn lambert_follows_hohmann_ellipse() {
+        let (r1, r2) = (1.0, 3.0);
+        let transfer = hohmann(MU, r1, r2);
+        let ellipse = Orbit::from_elements((r1 + r2) / 2.0, (r2 - r1) / (r2 + r1), 0.0, 0.0, 0.0, 0.0, 0.0);
+
+        //most of the way out, short of the half orbit where the transfer plane stops being defined
+        let flight_time = 0.9 * transfer.flight_time;
+        let (start, start_velocity) = state(&ellipse, 0.0);
+        let (end, end_velocity) = state(&ellipse, flight_time);
+
+        let (leave, arrive) = lambert(MU, start, end, flight_time).expect("no transfer");
+        assert_close(leave, start_velocity, 1e-9);
+        assert_close(arrive, end_velocity, 1e-9);
+
+        let departure = leave.length() - (MU / r1).sqrt();
+        assert!((departure - transfer.burns[0]).abs() < 1e-9, "{departure} vs {}", transfer.burns[0]);
+    }
+
+    #[test]
+    fn lambert_finds_hyperbolic_transfers() {
+        //far too quick for any ellipse between the two points
+        let hyperbola = Orbit::from_elements(-1.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        let flight_time = 2.0;
+        let (start, start_velocity) = state(&hyperbola, 0.0);
+        let (end, end_velocity) = state(&hyperbola, flight_time);
+
+        let (leave, arrive) = lambert(MU, start, end, flight_time).expect("no transfer");
+        assert_close(leave, start_velocity, 1e-9);
+        assert_close(arrive, end_velocity, 1e-9);
+        assert!(leave.length_squared() / 2.0 - MU / start.length() > 0.0, "transfer is bound");
+    }
+
+    #[test]
+    fn lambert_sweeps_past_half_an_orbit() {
+        //r2 is clockwise of r1, so the anticlockwise transfer sweeps three quarters of the way round
+        let circle = Orbit::from_elements(1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        let flight_time = 0.75 * circle.period(0.0, MU / G);
+        let (start, start_velocity) = state(&circle, 0.0);
+        let (end, end_velocity) = state(&circle, flight_time);
+        assert!(start.perp_dot(end) < 0.0);
+
+        let (leave, arrive) = lambert(MU, start, end, flight_time).expect("no transfer");
+        assert_close(leave, start_velocity, 1e-9);
+        assert_close(arrive, end_velocity, 1e-9);
+    }
+
+    #[test]
+    fn lambert_rejects_impossible_problems() {
+        let (r1, r2) = (DVec2::X, DVec2::new(0.0, 2.0));
+        assert!(lambert(MU, r1, r2, 0.0).is_none());
+        assert!(lambert(MU, DVec2::ZERO, r2, 1.0).is_none());
+        //no plane to transfer in between two points in the same direction
+        assert!(lambert(MU, r1, r1 * 2.0, 1.0).is_none());
+    }
+}
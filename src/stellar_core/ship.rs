@@ -1,14 +1,38 @@
 use bevy::prelude::*;
+use bevy::math::DVec2;
 use crate::stellar_core;
 
 mod thruster;
 mod path;
+pub mod maneuver;
+pub mod landing;
+mod aerodynamics;
+mod sail;
+pub mod planner;
+pub mod autopilot;
 
 use core::f32::consts::PI as PI;
 use thruster::EngineFlame as EngineFlame;
 use path::ShipPath as ShipPath;
 
-use stellar_core::solar_system::Mass;
+use stellar_core::solar_system::{Mass, Ephemeris, HoveredLagrange, orbit::G};
+use stellar_core::floating_origin::WorldPosition;
+use crate::stellar_utils::unit_conversion::au;
+use stellar_core::clock::SimulationClock;
+use stellar_core::navigation::{Airframe, Contact, ContactKind, Integrator, ManeuverNode, Patch, Prediction, PredictionConfig, PredictionTask, SoiChange, integrator::State};
+
+//longest step the integrators take, in seconds
+const MAX_STEP: f64 = 10.0;
+//cap on steps per fixed update, so high warp stretches the steps instead of stalling the frame
+const MAX_SUBSTEPS: f64 = 1000.0;
+//how little of the prediction can be left before it's worked out again, as a fraction
+const REPREDICT_REMAINING: f64 = 0.5;
+//main engine acceleration (m/s²) per hundred pixels between the ship and the cursor
+const ENGINE_ACCELERATION: f32 = 10.0;
+//how hard the brakes slow the ship against its reference body, in m/s²
+const BRAKE_ACCELERATION: f64 = 10.0;
+//fraction of the spin the brakes leave after each sixtieth of a second
+const BRAKE_SPIN_DAMPING: f32 = 0.95;
 
 pub struct ShipPlugin;
 impl Plugin for ShipPlugin {
@@ -17,29 +41,90 @@ impl Plugin for ShipPlugin {
             .add_plugins((
                 thruster::ThrusterPlugin,
                 ShipPath,
+                maneuver::ManeuverPlugin,
+                landing::LandingPlugin,
+                aerodynamics::AerodynamicsPlugin,
+                sail::SailPlugin,
+                planner::PlannerPlugin,
+                autopilot::AutopilotPlugin,
             ))
+            .add_event::<SoiChange>()
+            .init_resource::<PredictionConfig>()
             .add_systems(Startup, setup_ship)
-            .add_systems(Update, update_ship)
-            .add_systems(Update, ship_controls)
+            //the system has to be spawned before the ship can orbit it
+            .add_systems(PostStartup, enter_orbit)
+            .add_systems(FixedUpdate, (update_ship, update_osculating_orbit).chain())
+            .add_systems(FixedUpdate, (thrust, brake).after(update_ship).before(update_osculating_orbit))
+            .add_systems(Update, (interpolate_ship, ship_controls, cycle_target, log_soi_changes))
+            .add_systems(Update, (collect_prediction, start_prediction).chain().after(ship_controls))
             ;
     }
 }
 
 #[derive(Component, Debug)]
 pub struct Ship {
-    pub velocity: Vec2,
+    ///Simulated position in meters. The WorldPosition is interpolated between this and previous_position.
+    pub position: DVec2,
+    pub previous_position: DVec2,
+    ///Velocity in m/s.
+    pub velocity: DVec2,
     pub angular: f32,
-    pub future_path: Vec<Vec2>,
+    ///Acceleration (m/s²) the player is asking of the main engine. Set every frame, applied on the fixed timestep.
+    pub throttle: DVec2,
+    pub prediction: Prediction,
+    ///Something has happened that the prediction didn't know about, so it needs redoing.
+    pub prediction_stale: bool,
+    pub integrator: Integrator,
+    ///Fly on patched conics instead of feeling every body at once.
+    pub patched_conics: bool,
+    ///The conic the ship is coasting along. None whenever the engines have changed the velocity since.
+    pub patch: Option<Patch>,
+    ///Body whose sphere of influence the ship is in.
+    pub reference: Option<Entity>,
+    ///The conic the ship would follow around its reference body if nothing else pulled on it, refitted every step.
+    pub osculating: Option<Patch>,
+    ///Simulation time the ship's position and velocity are for, in seconds.
+    pub time: f64,
+    ///Planned burns, in time order.
+    pub maneuvers: Vec<ManeuverNode>,
+    ///Delta-v (m/s) still to go on the burn in progress, as a vector in the world.
+    pub burn: Option<DVec2>,
+    ///How the ship came down, for as long as it's sitting on a surface.
+    pub contact: Option<Contact>,
+    pub airframe: Airframe,
+    ///Temperature of the heat shield, in kelvin.
+    pub skin_temperature: f64,
 }
 
 impl Ship {
-    pub fn new() -> Self {
+    pub fn new(position: DVec2) -> Self {
         Ship { 
-            velocity: Vec2 {x: 0.0, y: -15.1 }, 
+            position,
+            previous_position: position,
+            velocity: DVec2::ZERO, 
             angular: 0.0,
-            future_path: Vec::new()
+            throttle: DVec2::ZERO,
+            prediction: Prediction::default(),
+            prediction_stale: true,
+            integrator: Integrator::default(),
+            patched_conics: false,
+            patch: None,
+            reference: None,
+            osculating: None,
+            time: 0.0,
+            maneuvers: Vec::new(),
+            burn: None,
+            contact: None,
+            airframe: Airframe::default(),
+            skin_temperature: stellar_core::navigation::drag::AMBIENT_TEMPERATURE,
         }
     }
+
+    ///Call after changing the velocity by anything other than gravity: the conic and the prediction no longer hold.
+    pub fn velocity_changed(&mut self) {
+        self.patch = None;
+        self.prediction_stale = true;
+    }
 }
 
 fn setup_ship(mut commands: Commands, asset_server : Res<AssetServer>) {
@@ -49,9 +134,10 @@ fn setup_ship(mut commands: Commands, asset_server : Res<AssetServer>) {
 
     //assemble the ship entity
     let _ship = commands.spawn((
-        Ship::new(),
+        Ship::new(DVec2::new(au(1.0), 0.0)),
+        WorldPosition(DVec2::new(au(1.0), 0.0)),
         Sprite { image: ship_image, custom_size: Some(Vec2::splat(10.)), ..default() },
-        Transform::from_xyz(1000.0, 100.0, 1.0),
+        Transform::from_xyz(0.0, 0.0, 1.0),
     ))
     .with_child( //the engine flame is a child because it allows custom placement of the plume
         EngineFlame::get_bundle(&engine_flame_image, 0, //main engine
@@ -79,71 +165,266 @@ fn setup_ship(mut commands: Commands, asset_server : Res<AssetServer>) {
 
 }
 
-fn update_ship_position(mut ship_query: Query<(&mut Ship, &mut Transform)>) {
-    let Ok((ship, mut transform)) = ship_query.get_single_mut()
-        else {return};
+//puts the ship on a circular orbit around whatever pulls on it hardest
+fn enter_orbit(
+    clock: Res<SimulationClock>,
+    mut ship_query: Query<&mut Ship>,
+    bodies: Query<(&Mass, &WorldPosition), Without<Ship>>
+) {
+    let Ok(mut ship) = ship_query.get_single_mut() else { return };
+    ship.time = clock.time();
+
+    let Some((mass, position)) = strongest_pull(ship.position, bodies.iter()) else { return };
 
-    transform.translation = 
-        Vec3 {
-            x: transform.translation.x + ship.velocity.x, 
-            y: transform.translation.y + ship.velocity.y,
-            z: transform.translation.z,
-        };
+    let radius = ship.position - position;
+    let speed = (G * mass / radius.length()).sqrt();
+    //prograde is anticlockwise, like the planets
+    ship.velocity = radius.perp().normalize_or_zero() * speed;
 }
 
-//process gravity for the ship
+//mass and position (m) of the body with the strongest gravity at this position
+fn strongest_pull<'a>(
+    position: DVec2, bodies: impl Iterator<Item = (&'a Mass, &'a WorldPosition)>
+) -> Option<(f64, DVec2)> {
+    bodies
+        .map(|(mass, body)| (**mass, **body))
+        .max_by(|(m1, p1), (m2, p2)| {
+            let pull1 = m1 / p1.distance_squared(position);
+            let pull2 = m2 / p2.distance_squared(position);
+            pull1.total_cmp(&pull2)
+        })
+}
+
+//process gravity for the ship. runs on the fixed timestep so the result doesn't depend on the framerate
 fn update_ship(
-    mut ship_query: Query<(&mut stellar_core::ship::Ship, &mut Transform)>, 
-    bodies_query: Query<(&stellar_core::solar_system::planet::Planet, &Transform), Without<stellar_core::ship::Ship>>,
-    stars_query: Query<(&stellar_core::solar_system::celestial_body::Star, &Transform), Without<stellar_core::ship::Ship>>,
-    bodies: Query<(&Mass, &Transform)>
+    clock: Res<SimulationClock>,
+    ephemeris: Res<Ephemeris>,
+    config: Res<PredictionConfig>,
+    mut soi_changes: EventWriter<SoiChange>,
+    mut ship_query: Query<(Entity, &mut stellar_core::ship::Ship, &mut Transform)>
 ) {
     //unpack and error handle the tuple
-    let Ok((mut ship, mut transform)) = ship_query.get_single_mut() 
+    let Ok((entity, mut ship, mut transform)) = ship_query.get_single_mut() 
         else {return};
 
-    let mut points: Vec<Vec2> = Vec::new();
-    let mut current_point = transform.translation.xy();
-    let mut current_velocity = ship.velocity;
+    //catch up with the clock, which ticked at the start of this step
+    let t = ship.time;
+    let dt = clock.time() - t;
 
-    let path_length = 200;
+    //the bodies move along their orbits during the step
+    let gravity = |position: DVec2, t: f64| {
+        stellar_core::navigation::ephemeris_acceleration(&ephemeris, position, t, config.softening)
+    };
 
-    for i in 0..path_length {
-        // Calculate the new velocity based on gravitational attraction
-        let new_velocity = 
-            stellar_core::navigation::calculate_acceleration(
-                &current_point, &bodies_query.iter().collect(), &stars_query.iter().collect()
-            )
-            + current_velocity; // Add it to the current velocity
+    let integrator = ship.integrator;
+    let state = State::new(ship.position, ship.velocity);
 
-        //on the first run, update the ship values.
-        if i == 0 {
-            transform.translation.x += new_velocity.x;
-            transform.translation.y += new_velocity.y;
+    //no conic fits a ship standing on the ground, so it's integrated until it lifts off
+    let (state, patch) = match ship.patched_conics && ship.contact.is_none() {
+        //on rails: the conic is exact, however long the step
+        true => {
+            let patch = ship.patch.take().or_else(|| Patch::fit(&ephemeris, state, t));
+            let Some(mut patch) = patch else { return };
 
-            ship.velocity = new_velocity;
-        }
+            change_reference(entity, &mut ship, patch.reference, t, &mut soi_changes);
+            for (_, to, crossed) in patch.coast(&ephemeris, t, dt) {
+                change_reference(entity, &mut ship, to, crossed, &mut soi_changes);
+            }
+
+            (patch.state_at(&ephemeris, t + dt), Some(patch))
+        },
+        false => {
+            let state = integrator.advance(state, t, dt, MAX_STEP.max(dt / MAX_SUBSTEPS), gravity);
+            if let Some(dominant) = ephemeris.dominant_body(state.position, t + dt) {
+                change_reference(entity, &mut ship, dominant, t + dt, &mut soi_changes);
+            }
+
+            (state, None)
+        },
+    };
+
+    ship.previous_position = ship.position;
+    ship.position = state.position;
+    ship.velocity = state.velocity;
+    ship.time = t + dt;
+    //angular is in radians per tick, a sixtieth of a second
+    transform.rotation *= Quat::from_rotation_z(ship.angular * (dt * 60.0) as f32);
+
+    ship.patch = patch;
+}
+
+//fits the osculating orbit to wherever gravity and the engines have left the ship
+fn update_osculating_orbit(ephemeris: Res<Ephemeris>, mut ship_query: Query<&mut Ship>) {
+    let Ok(mut ship) = ship_query.get_single_mut() else { return };
+
+    let state = State::new(ship.position, ship.velocity);
+    ship.osculating = match ship.contact {
+        Some(_) => None,
+        None => ship.reference.and_then(|reference| Patch::fit_around(&ephemeris, reference, state, ship.time)),
+    };
+}
+
+//records a change of the ship's dominant body, and lets everyone else know
+fn change_reference(
+    entity: Entity, ship: &mut Ship, to: Entity, time: f64, soi_changes: &mut EventWriter<SoiChange>
+) {
+    if ship.reference == Some(to) {
+        return;
+    }
+
+    soi_changes.send(SoiChange { ship: entity, from: ship.reference, to, time });
+    ship.reference = Some(to);
+    ship.prediction_stale = true;
+}
+
+//T cycles the target through the planets and moons, then back to nothing.
+//with the cursor over a Lagrange point it targets that instead
+fn cycle_target(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    ephemeris: Res<Ephemeris>,
+    hovered: Res<HoveredLagrange>,
+    mut config: ResMut<PredictionConfig>
+) {
+    if !keyboard.just_pressed(KeyCode::KeyT) {
+        return;
+    }
+
+    if let Some(point) = hovered.0 {
+        config.target = Some(point);
+        info!("target: {:?}", config.target);
+        return;
+    }
+
+    let mut bodies: Vec<Entity> = ephemeris.iter()
+        .filter(|(_, body)| body.orbit.is_some())
+        .map(|(entity, _)| entity)
+        .collect();
+    bodies.sort();
+
+    config.target = match config.target.and_then(|target| bodies.iter().position(|body| *body == target)) {
+        Some(i) => bodies.get(i + 1).copied(),
+        None => bodies.first().copied(),
+    };
+    info!("target: {:?}", config.target);
+}
+
+//kicks off a new prediction in the background when the old one is stale or running out.
+//only one runs at a time, so a ship under thrust re-predicts as fast as the predictions come back
+fn start_prediction(
+    mut commands: Commands,
+    ephemeris: Res<Ephemeris>,
+    config: Res<PredictionConfig>,
+    mut ship_query: Query<(Entity, &mut Ship), Without<PredictionTask>>
+) {
+    let Ok((entity, mut ship)) = ship_query.get_single_mut() else { return };
+
+    //nothing to predict while sitting on the ground
+    if ship.contact.is_some() {
+        ship.prediction = Prediction::default();
+        return;
+    }
+
+    let running_low = ship.prediction.remaining(ship.time) < REPREDICT_REMAINING;
+    if !(ship.prediction_stale || running_low || config.is_changed()) {
+        return;
+    }
+    ship.prediction_stale = false;
+
+    let state = State::new(ship.position, ship.velocity);
+    let patch = match ship.patched_conics {
+        true => ship.patch.clone().or_else(|| Patch::fit(&ephemeris, state, ship.time)),
+        false => None,
+    };
+
+    commands.entity(entity).insert(
+        PredictionTask::spawn(ephemeris.clone(), state, ship.time, patch, ship.maneuvers.clone(), ship.airframe, config.clone())
+    );
+}
+
+//picks up finished predictions
+fn collect_prediction(mut commands: Commands, mut ship_query: Query<(Entity, &mut Ship, &mut PredictionTask)>) {
+    for (entity, mut ship, mut task) in ship_query.iter_mut() {
+        let Some(prediction) = task.poll() else { continue };
+
+        ship.prediction = prediction;
+        commands.entity(entity).remove::<PredictionTask>();
+    }
+}
+
+fn log_soi_changes(mut soi_changes: EventReader<SoiChange>) {
+    for change in soi_changes.read() {
+        info!("{:?} left the sphere of influence of {:?} for {:?} at t = {:.0} s", change.ship, change.from, change.to, change.time);
+    }
+}
+
+//smooths the ship's rendered position between the last two fixed steps
+fn interpolate_ship(time: Res<Time<Fixed>>, mut ship_query: Query<(&Ship, &mut WorldPosition)>) {
+    let Ok((ship, mut position)) = ship_query.get_single_mut() 
+        else {return};
+
+    **position = ship.previous_position.lerp(ship.position, time.overstep_fraction_f64());
+}
 
-        //update velocity and points
-        current_velocity = new_velocity;
-        current_point += new_velocity;
-        //push the position to the vec
-        points.push(current_point);
+//fires the main engine for as long as the player holds it open
+fn thrust(time: Res<Time<Fixed>>, clock: Res<SimulationClock>, mut ship_query: Query<&mut Ship>) {
+    let Ok(mut ship) = ship_query.get_single_mut() else { return };
+    if ship.throttle == DVec2::ZERO || clock.paused {
+        return;
     }
-    transform.rotation *= Quat::from_rotation_z(ship.angular);
-    ship.future_path = points;
+
+    let dt = time.delta_secs_f64() * clock.warp;
+    let throttle = ship.throttle;
+    ship.velocity += throttle * dt;
+    ship.velocity_changed();
+}
+
+//right click slows the ship down relative to whatever it's orbiting, and stops it spinning
+fn brake(
+    time: Res<Time<Fixed>>,
+    clock: Res<SimulationClock>,
+    ephemeris: Res<Ephemeris>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut ship_query: Query<&mut Ship>
+) {
+    let Ok(mut ship) = ship_query.get_single_mut() else { return };
+    if !mouse_buttons.pressed(MouseButton::Right) || clock.paused || clock.on_rails() || ship.contact.is_some() {
+        return;
+    }
+
+    let dt = time.delta_secs_f64() * clock.warp;
+    let frame_velocity = ship.reference
+        .and_then(|reference| ephemeris.state_at(reference, ship.time))
+        .map_or(DVec2::ZERO, |(_, velocity)| velocity);
+
+    let relative = ship.velocity - frame_velocity;
+    ship.velocity -= relative.clamp_length_max(BRAKE_ACCELERATION * dt);
+    ship.angular *= BRAKE_SPIN_DAMPING.powf(time.delta_secs() * 60.0);
+    ship.velocity_changed();
 }
 
 fn ship_controls(
+    clock: Res<SimulationClock>,
     mouse_buttons: Res<ButtonInput<MouseButton>>,
     keyboard: Res<ButtonInput<KeyCode>>,
+    autopilot: Res<autopilot::Autopilot>,
     mut ship_query: Query<(&mut Ship, &mut Transform)>,
     mut engines: Query<&mut EngineFlame, Without<Ship>>,
     q_windows: Query<&Window, With<bevy::window::PrimaryWindow>>
 ) {
     let Ok((mut ship, transform)) = ship_query.get_single_mut() else { return };
     let Ok(window) = q_windows.get_single() else { return };
+    ship.throttle = DVec2::ZERO;
+
+    //no flying while paused, warping on rails, or wrecked
+    let crashed = ship.contact.is_some_and(|contact| contact.kind == ContactKind::Crash);
+    if clock.paused || clock.on_rails() || crashed {
+        for mut e in engines.iter_mut() {
+            e.active = false;
+        }
+        return;
+    }
 
+    let burning = ship.burn.is_some();
     let mut button_pressed = false;
     let mut toggle_engine = |id: i32, state| {
         button_pressed = true;
@@ -154,12 +435,17 @@ fn ship_controls(
         }
     };
 
+    //automated burns fire the main engine, and the autopilot turns the ship with the side ones
+    if burning {
+        toggle_engine(0, true);
+    }
+    if let Some(id) = autopilot.rcs {
+        toggle_engine(id, true);
+    }
+
     if mouse_buttons.pressed(MouseButton::Right) {
         toggle_engine(1, true);
         toggle_engine(2, true);
-
-        ship.angular *= 0.95;
-        ship.velocity *= 0.95;
     }
 
     if mouse_buttons.pressed(MouseButton::Left) {
@@ -175,9 +461,9 @@ fn ship_controls(
         let velocity_modifier = Vec2 { 
             x: (world_pos.x - transform.translation.x), 
             y: -(world_pos.y - transform.translation.y) 
-        } / 1.0e3;
+        } / 100.0 * ENGINE_ACCELERATION;
 
-        ship.velocity += velocity_modifier;
+        ship.throttle = velocity_modifier.as_dvec2();
 
         if ship.velocity.length_squared() > 0.0 {
             let nrm = velocity_modifier.normalize();
@@ -202,7 +488,19 @@ fn ship_controls(
     }
 
     if mouse_buttons.just_pressed(MouseButton::Middle) {
-        dbg!(transform.translation);
+        info!("ship position: ({:.0}, {:.0}) m", ship.position.x, ship.position.y);
+    }
+
+    if keyboard.just_pressed(KeyCode::KeyI) {
+        ship.integrator = ship.integrator.next();
+        ship.prediction_stale = true;
+        info!("integrator: {:?}", ship.integrator);
+    }
+
+    if keyboard.just_pressed(KeyCode::KeyC) {
+        ship.patched_conics = !ship.patched_conics;
+        ship.velocity_changed();
+        info!("patched conics: {}", ship.patched_conics);
     }
 
     if keyboard.pressed(KeyCode::KeyQ) {
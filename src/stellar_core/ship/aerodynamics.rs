@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+
+use crate::stellar_core;
+use stellar_core::clock::{SimulationClock, MAX_PHYSICS_WARP};
+use stellar_core::navigation::{drag::{air_at, apply_drag}, integrator::State};
+use stellar_core::solar_system::Ephemeris;
+use stellar_core::ship::Ship;
+
+//how far the ship may wander from its predicted path in the air before that's worked out again, in scale heights
+const MAX_DRIFT: f64 = 0.1;
+
+pub struct AerodynamicsPlugin;
+impl Plugin for AerodynamicsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            //drag acts on the velocity gravity leaves, and has to be felt before the ship can touch down
+            .add_systems(FixedUpdate, update_aerodynamics
+                .after(super::update_ship)
+                .before(super::landing::resolve_contact)
+                .before(super::update_osculating_orbit)
+            )
+            ;
+    }
+}
+
+//slows the ship in the air and heats the shield, cooling it again outside
+fn update_aerodynamics(
+    time: Res<Time<Fixed>>,
+    mut clock: ResMut<SimulationClock>,
+    ephemeris: Res<Ephemeris>,
+    mut ship_query: Query<&mut Ship>
+) {
+    let Ok(mut ship) = ship_query.get_single_mut() else { return };
+
+    let dt = match clock.paused {
+        true => 0.0,
+        false => time.delta_secs_f64() * clock.warp,
+    };
+
+    let state = State::new(ship.position, ship.velocity);
+    let air = air_at(&ephemeris, state, ship.time);
+
+    let heat_flux = air.map_or(0.0, |air| ship.airframe.heat_flux(air.density, air.velocity.length()));
+    let was_safe = ship.skin_temperature <= ship.airframe.max_temperature;
+    ship.skin_temperature = ship.airframe.skin_temperature(ship.skin_temperature, heat_flux, dt);
+    if was_safe && ship.skin_temperature > ship.airframe.max_temperature {
+        warn!("heat shield over its rated {:.0} K", ship.airframe.max_temperature);
+    }
+
+    let Some(air) = air else { return };
+
+    //a warped step could carry the ship clean through the atmosphere, so the air holds the warp down
+    if clock.warp > MAX_PHYSICS_WARP {
+        clock.warp = MAX_PHYSICS_WARP;
+        info!("warp dropped to {MAX_PHYSICS_WARP}x in the air");
+    }
+
+    if ship.contact.is_some() {
+        return;
+    }
+
+    ship.velocity = apply_drag(&ephemeris, &ship.airframe, state, ship.time, dt).velocity;
+    ship.patch = None;
+
+    //the prediction flies the same drag, so it only needs redoing once the ship has wandered off it
+    let drift = ship.prediction.position_at(ship.time)
+        .map_or(f64::INFINITY, |predicted| predicted.distance(ship.position));
+    if drift > MAX_DRIFT * air.scale_height {
+        ship.prediction_stale = true;
+    }
+}
@@ -0,0 +1,608 @@
+use std::f64::consts::{PI, TAU};
+
+use bevy::prelude::*;
+use bevy::math::DVec2;
+
+use crate::stellar_core;
+use stellar_core::clock::SimulationClock;
+use stellar_core::floating_origin::FloatingOrigin;
+use stellar_core::navigation::{Patch, PredictionConfig, integrator::State};
+use stellar_core::navigation::maneuver::{local_frame, BURN_ACCELERATION};
+use stellar_core::navigation::transfer::{hohmann, lambert};
+use stellar_core::solar_system::{Ephemeris, Orbit, to_meters, orbit::G};
+use stellar_core::ship::Ship;
+use super::maneuver::WARP_LEAD_STEPS;
+
+//flight times tried for a rendezvous, as fractions of the hohmann flight time out to the target
+const RENDEZVOUS_TIME_RANGE: (f64, f64) = (0.3, 1.7);
+//how many flight times a rendezvous tries
+const RENDEZVOUS_RESOLUTION: usize = 64;
+//how much the thrusters change the spin each tick, the same as Q and E
+const RCS_RATE: f32 = 0.002;
+//spin (radians per tick) that counts as stopped
+const ROTATION_TOLERANCE: f32 = 1e-4;
+//how hard holding attitude turns back towards the heading, in spin per radian off at 1x
+const HOLD_GAIN: f32 = 0.1;
+
+pub struct AutopilotPlugin;
+impl Plugin for AutopilotPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Autopilot>()
+            .add_event::<AutopilotProgress>()
+            .add_event::<AutopilotComplete>()
+            .add_event::<AutopilotAborted>()
+            .add_systems(Update, (autopilot_controls, log_autopilot))
+            //the autopilot hands its burns to the same executor as the maneuver nodes
+            .add_systems(FixedUpdate, fly
+                .after(super::update_ship)
+                .before(super::maneuver::execute_burns)
+                .before(super::update_osculating_orbit)
+            )
+            ;
+    }
+}
+
+///What the autopilot can be asked to do.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AutopilotCommand {
+    ///Make the orbit a circle at whichever apsis comes next.
+    Circularize,
+    ///Burn at apoapsis for a periapsis this far (m) from the reference body's centre.
+    SetPeriapsis(f64),
+    ///Burn at periapsis for an apoapsis this far (m) from the reference body's centre.
+    SetApoapsis(f64),
+    ///Take on the size and shape of the target's orbit around the same body, then turn it to point the same way.
+    MatchOrbit(Entity),
+    ///Fly to the target and stop alongside it. A body's own pull bends the last of the way in, so this
+    ///is surest with Lagrange points.
+    Rendezvous(Entity),
+    ///Stop the ship spinning.
+    KillRotation,
+    ///Keep the ship pointing the way it was when this was given, until told otherwise.
+    HoldAttitude,
+}
+
+impl AutopilotCommand {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AutopilotCommand::Circularize => "CIRCULARIZE",
+            AutopilotCommand::SetPeriapsis(_) => "SET PERIAPSIS",
+            AutopilotCommand::SetApoapsis(_) => "SET APOAPSIS",
+            AutopilotCommand::MatchOrbit(_) => "MATCH ORBIT",
+            AutopilotCommand::Rendezvous(_) => "RENDEZVOUS",
+            AutopilotCommand::KillRotation => "KILL ROTATION",
+            AutopilotCommand::HoldAttitude => "HOLD ATTITUDE",
+        }
+    }
+}
+
+///Sent every fixed step the autopilot is flying.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct AutopilotProgress {
+    pub command: AutopilotCommand,
+    ///How much of the command is done, from 0 to 1.
+    pub progress: f64,
+    ///Seconds until the next burn, while it's coasting up to one.
+    pub countdown: Option<f64>,
+}
+
+///Sent when a command has been carried out.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct AutopilotComplete {
+    pub command: AutopilotCommand,
+}
+
+///Sent when a command can't be carried out, or is cancelled.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct AutopilotAborted {
+    pub command: AutopilotCommand,
+    pub reason: &'static str,
+}
+
+///Which apsis a burn goes at.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Apsis {
+    Periapsis,
+    Apoapsis,
+    ///Whichever comes first.
+    Next,
+    ///Whichever is nearer this distance (m) from the centre.
+    Near(f64),
+}
+
+//one leg of a command
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Step {
+    //burn along the track at an apsis so the other apsis ends up this far out, or the same distance for a circle
+    Burn { at: Apsis, other: Option<f64> },
+    //burn where the orbit crosses the target's, onto the target's
+    Align { target: Entity },
+    //burn now onto the cheapest transfer to the target
+    Intercept { target: Entity },
+    //match the target's velocity when the transfer gets there, delta_v (m/s) being what that should take
+    Arrive { target: Entity, time: f64, delta_v: f64 },
+    KillRotation,
+    Hold { heading: f32 },
+}
+
+///The command the autopilot is flying, and how far through it is.
+#[derive(Resource, Debug, Default)]
+pub struct Autopilot {
+    pub command: Option<AutopilotCommand>,
+    ///The thruster the autopilot is firing to turn the ship, if any, for the flames.
+    pub rcs: Option<i32>,
+    steps: Vec<Step>,
+    //how many steps the command started with
+    total_steps: usize,
+    //size of the burn in progress when it was lit, in m/s
+    burning: Option<f64>,
+}
+
+impl Autopilot {
+    fn engage(&mut self, command: AutopilotCommand, steps: Vec<Step>) {
+        self.command = Some(command);
+        self.total_steps = steps.len();
+        self.steps = steps;
+        self.burning = None;
+        self.rcs = None;
+    }
+
+    fn disengage(&mut self) {
+        *self = Autopilot::default();
+    }
+}
+
+//V circularizes, F and Y put the periapsis or apoapsis at the cursor, M matches the target's orbit,
+//R flies to the target, B stops the spin and G holds the heading. Backspace hands back control
+fn autopilot_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    origin: Res<FloatingOrigin>,
+    ephemeris: Res<Ephemeris>,
+    config: Res<PredictionConfig>,
+    mut autopilot: ResMut<Autopilot>,
+    mut aborted: EventWriter<AutopilotAborted>,
+    ship_query: Query<(&Ship, &Transform)>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    q_windows: Query<&Window, With<bevy::window::PrimaryWindow>>
+) {
+    let Ok((ship, transform)) = ship_query.get_single() else { return };
+
+    if keyboard.just_pressed(KeyCode::Backspace) {
+        if let Some(command) = autopilot.command {
+            aborted.send(AutopilotAborted { command, reason: "cancelled" });
+        }
+        autopilot.disengage();
+        return;
+    }
+
+    //how far the cursor is from the centre of the body the ship is orbiting
+    let cursor_radius = || -> Option<f64> {
+        let (camera, camera_transform) = camera_query.get_single().ok()?;
+        let cursor_pos = q_windows.get_single().ok()?.cursor_position()?;
+        let cursor_pos = camera.viewport_to_world_2d(camera_transform, cursor_pos).ok()?;
+        let centre = ephemeris.position_at(ship.reference?, ship.time)?;
+
+        Some((origin.position + to_meters(cursor_pos)).distance(centre))
+    };
+
+    let command = if keyboard.just_pressed(KeyCode::KeyV) {
+        AutopilotCommand::Circularize
+    } else if keyboard.just_pressed(KeyCode::KeyF) {
+        let Some(radius) = cursor_radius() else { return };
+        AutopilotCommand::SetPeriapsis(radius)
+    } else if keyboard.just_pressed(KeyCode::KeyY) {
+        let Some(radius) = cursor_radius() else { return };
+        AutopilotCommand::SetApoapsis(radius)
+    } else if keyboard.just_pressed(KeyCode::KeyM) {
+        let Some(target) = config.target else { return };
+        AutopilotCommand::MatchOrbit(target)
+    } else if keyboard.just_pressed(KeyCode::KeyR) {
+        let Some(target) = config.target else { return };
+        AutopilotCommand::Rendezvous(target)
+    } else if keyboard.just_pressed(KeyCode::KeyB) {
+        AutopilotCommand::KillRotation
+    } else if keyboard.just_pressed(KeyCode::KeyG) {
+        AutopilotCommand::HoldAttitude
+    } else {
+        return;
+    };
+
+    if let Some(previous) = autopilot.command {
+        aborted.send(AutopilotAborted { command: previous, reason: "replaced" });
+    }
+
+    let steps = match command {
+        AutopilotCommand::Circularize => vec![Step::Burn { at: Apsis::Next, other: None }],
+        AutopilotCommand::SetPeriapsis(radius) => vec![Step::Burn { at: Apsis::Apoapsis, other: Some(radius) }],
+        AutopilotCommand::SetApoapsis(radius) => vec![Step::Burn { at: Apsis::Periapsis, other: Some(radius) }],
+        AutopilotCommand::MatchOrbit(target) => {
+            let orbit = ephemeris.body(target)
+                .and_then(|body| body.orbit.as_ref())
+                .filter(|_| ship.reference.is_some() && ephemeris.parent(target) == ship.reference);
+            let Some(orbit) = orbit else {
+                aborted.send(AutopilotAborted { command, reason: "target doesn't orbit the same body" });
+                autopilot.disengage();
+                return;
+            };
+
+            //out to the target's apoapsis from the periapsis, then bring the periapsis round from out there.
+            //that leaves the same ellipse turned some way round, which crosses the target's wherever it's
+            //turned halfway, and one burn there swings it the rest of the way
+            let (apoapsis, periapsis) = (orbit.apoapsis(), orbit.periapsis);
+            vec![
+                Step::Burn { at: Apsis::Periapsis, other: Some(apoapsis) },
+                Step::Burn { at: Apsis::Near(apoapsis), other: Some(periapsis) },
+                Step::Align { target },
+            ]
+        },
+        AutopilotCommand::Rendezvous(target) => vec![Step::Intercept { target }],
+        AutopilotCommand::KillRotation => vec![Step::KillRotation],
+        AutopilotCommand::HoldAttitude => vec![Step::Hold { heading: transform.rotation.to_euler(EulerRot::XYZ).2 }],
+    };
+
+    info!("autopilot: {}", command.label());
+    autopilot.engage(command, steps);
+}
+
+//works through the command a step at a time, handing burns to the maneuver executor and turning with the thrusters
+fn fly(
+    time: Res<Time<Fixed>>,
+    mut clock: ResMut<SimulationClock>,
+    ephemeris: Res<Ephemeris>,
+    mut autopilot: ResMut<Autopilot>,
+    mut progress: EventWriter<AutopilotProgress>,
+    mut complete: EventWriter<AutopilotComplete>,
+    mut aborted: EventWriter<AutopilotAborted>,
+    mut ship_query: Query<(&mut Ship, &Transform)>
+) {
+    let Some(command) = autopilot.command else { return };
+    let Ok((mut ship, transform)) = ship_query.get_single_mut() else { return };
+
+    let Some(step) = autopilot.steps.first().copied() else {
+        complete.send(AutopilotComplete { command });
+        autopilot.disengage();
+        return;
+    };
+
+    let mut abort = |autopilot: &mut Autopilot, reason| {
+        aborted.send(AutopilotAborted { command, reason });
+        autopilot.disengage();
+    };
+
+    let (total, done) = (autopilot.total_steps.max(1) as f64, (autopilot.total_steps - autopilot.steps.len()) as f64);
+    let report = |step_progress: f64, countdown| AutopilotProgress {
+        command,
+        progress: (done + step_progress) / total,
+        countdown,
+    };
+
+    //a burn the autopilot lit is running or has just burned out
+    if let Some(size) = autopilot.burning {
+        match ship.burn {
+            Some(remaining) => {
+                progress.send(report(1.0 - remaining.length() / size.max(f64::EPSILON), None));
+            },
+            None => {
+                autopilot.burning = None;
+                autopilot.steps.remove(0);
+                progress.send(report(1.0, None));
+            },
+        };
+        return;
+    }
+
+    match step {
+        Step::KillRotation | Step::Hold { .. } => {
+            if clock.paused {
+                return;
+            }
+            //spin is per tick of simulated time, so under warp each step turns the ship through many of them
+            let ticks = (time.delta_secs_f64() * clock.warp * 60.0) as f32;
+            let goal = match step {
+                Step::Hold { heading } => {
+                    let current = transform.rotation.to_euler(EulerRot::XYZ).2;
+                    let off = (heading - current + PI as f32).rem_euclid(TAU as f32) - PI as f32;
+                    (off * HOLD_GAIN).clamp(-RCS_RATE * 10.0, RCS_RATE * 10.0) / ticks
+                },
+                _ => 0.0,
+            };
+
+            let change = (goal - ship.angular).clamp(-RCS_RATE, RCS_RATE);
+            ship.angular += change;
+            //q, engine 1, spins the ship up anticlockwise and e, engine 2, clockwise.
+            //nudges too small to stop a spin are left out, so the flames don't flicker while holding
+            autopilot.rcs = match change {
+                c if c > ROTATION_TOLERANCE => Some(1),
+                c if c < -ROTATION_TOLERANCE => Some(2),
+                _ => None,
+            };
+
+            if step == Step::KillRotation && ship.angular.abs() < ROTATION_TOLERANCE {
+                ship.angular = 0.0;
+                autopilot.rcs = None;
+                autopilot.steps.remove(0);
+            }
+            progress.send(report(0.0, None));
+            return;
+        },
+        _ => {},
+    }
+
+    if ship.contact.is_some() {
+        abort(&mut autopilot, "landed");
+        return;
+    }
+    //a maneuver node is burning, so wait for it to finish
+    if ship.burn.is_some() {
+        return;
+    }
+
+    let t = ship.time;
+    let planned = match step {
+        Step::Burn { at, other } => apsis_burn(&ephemeris, &ship, at, other),
+        Step::Align { target } => align_burn(&ephemeris, &ship, target),
+        Step::Intercept { target } => intercept(&ephemeris, &ship, target).map(|(delta_v, arrive)| {
+            //the burn's lit straight away, and the arrival becomes the next step
+            autopilot.steps.insert(1, arrive);
+            autopilot.total_steps += 1;
+            (0.0, delta_v)
+        }),
+        Step::Arrive { target, time, delta_v } => ephemeris.state_at(target, t)
+            .map(|(_, velocity)| (time - t - delta_v / BURN_ACCELERATION / 2.0, velocity - ship.velocity))
+            .ok_or("target is gone"),
+        Step::KillRotation | Step::Hold { .. } => unreachable!(),
+    };
+    let (lead, delta_v) = match planned {
+        Ok(planned) => planned,
+        Err(reason) => {
+            abort(&mut autopilot, reason);
+            return;
+        },
+    };
+
+    //hold off until half the burn is left before the point it's centred on, as the nodes do
+    let lead = match step {
+        Step::Burn { .. } | Step::Align { .. } => lead - delta_v.length() / BURN_ACCELERATION / 2.0,
+        _ => lead,
+    };
+
+    if lead > 0.0 {
+        if clock.warp > 1.0 && lead < clock.warp * time.delta_secs_f64() * WARP_LEAD_STEPS {
+            clock.step_warp(-1);
+        }
+        progress.send(report(0.0, Some(lead)));
+        return;
+    }
+
+    autopilot.burning = Some(delta_v.length());
+    ship.burn = (delta_v.length_squared() > 0.0).then_some(delta_v);
+    ship.prediction_stale = true;
+    progress.send(report(0.0, None));
+}
+
+//seconds until the apsis and the burn along the track there (m/s, in the world) that puts the far side of the
+//orbit at other, or makes a circle
+fn apsis_burn(ephemeris: &Ephemeris, ship: &Ship, at: Apsis, other: Option<f64>) -> Result<(f64, DVec2), &'static str> {
+    let state = State::new(ship.position, ship.velocity);
+    let reference = ship.reference.ok_or("not orbiting anything")?;
+    let patch = Patch::fit_around(ephemeris, reference, state, ship.time).ok_or("not orbiting anything")?;
+    let orbit = &patch.orbit;
+    let parent_mass = ephemeris.mass(reference);
+    let mu = G * parent_mass;
+
+    let periapsis = orbit.time_to_periapsis(ship.time, 0.0, parent_mass).map(|time| (time, orbit.periapsis));
+    let apoapsis = orbit.time_to_apoapsis(ship.time, 0.0, parent_mass).map(|time| (time, orbit.apoapsis()));
+
+    let (lead, radius) = match at {
+        Apsis::Periapsis => periapsis.ok_or("periapsis is behind")?,
+        Apsis::Apoapsis => apoapsis.ok_or("no apoapsis")?,
+        Apsis::Next => [periapsis, apoapsis].into_iter().flatten()
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .ok_or("no apsis ahead")?,
+        Apsis::Near(distance) => [periapsis, apoapsis].into_iter().flatten()
+            .min_by(|(_, a), (_, b)| (a - distance).abs().total_cmp(&(b - distance).abs()))
+            .ok_or("no apsis ahead")?,
+    };
+
+    let change = apsis_speed_change(mu, radius, orbit.semi_major_axis(), other.unwrap_or(radius));
+    let (prograde, _) = local_frame(ephemeris, state, ship.time);
+    Ok((lead, prograde * change))
+}
+
+//change of speed (m/s) at an apsis this far (m) from the centre, on an orbit with this semi-major axis (m),
+//that puts the far side of the orbit at other (m)
+fn apsis_speed_change(mu: f64, radius: f64, semi_major_axis: f64, other: f64) -> f64 {
+    //vis-viva at the apsis, before and after
+    let before = (mu * (2.0 / radius - 1.0 / semi_major_axis)).sqrt();
+    let after = (mu * (2.0 / radius - 2.0 / (radius + other))).sqrt();
+
+    after - before
+}
+
+//seconds until the ship's orbit crosses the target's, and the burn (m/s, in the world) there onto the target's orbit.
+//where they don't quite cross it burns where they come closest, which still matches the shape and the direction
+fn align_burn(ephemeris: &Ephemeris, ship: &Ship, target: Entity) -> Result<(f64, DVec2), &'static str> {
+    let state = State::new(ship.position, ship.velocity);
+    let reference = ship.reference.ok_or("not orbiting anything")?;
+    let patch = Patch::fit_around(ephemeris, reference, state, ship.time).ok_or("not orbiting anything")?;
+    let orbit = &patch.orbit;
+    let goal = ephemeris.body(target).and_then(|body| body.orbit.as_ref()).ok_or("target is gone")?;
+    let parent_mass = ephemeris.mass(reference);
+    let mu = G * parent_mass;
+
+    if direction(orbit) != direction(goal) {
+        return Err("target goes round the other way");
+    }
+
+    let (lead, angle) = crossings(orbit, goal).into_iter()
+        .filter_map(|angle| {
+            let lead = orbit.time_to_true_anomaly(ship.time, orbit.true_anomaly_towards(angle), 0.0, parent_mass)?;
+            Some((lead, angle))
+        })
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .ok_or("orbits don't cross")?;
+
+    //velocity (m/s) going round either orbit through that direction
+    let velocity = |orbit: &Orbit| {
+        let (sin, cos) = orbit.true_anomaly_towards(angle).sin_cos();
+        let speed = (mu / orbit.semi_latus()).sqrt();
+        let out = DVec2::from_angle(angle);
+
+        out * speed * orbit.eccentricity * sin + out.perp() * direction(orbit) * speed * (1.0 + orbit.eccentricity * cos)
+    };
+
+    Ok((lead, velocity(goal) - velocity(orbit)))
+}
+
+//anticlockwise or clockwise
+fn direction(orbit: &Orbit) -> f64 {
+    orbit.inclination.cos().signum()
+}
+
+//the two directions (rad, from +X) in which two orbits in the plane around the same body are the same distance out.
+//where they don't quite cross, both are where they come closest
+fn crossings(orbit: &Orbit, goal: &Orbit) -> [f64; 2] {
+    //each orbit as r = p / (1 + e cos(θ - φ)), φ being where the periapsis points
+    let conic = |orbit: &Orbit| {
+        let periapsis = orbit.position_at_true_anomaly(0.0);
+        (orbit.semi_latus(), orbit.eccentricity, periapsis.y.atan2(periapsis.x))
+    };
+    let ((p1, e1, w1), (p2, e2, w2)) = (conic(orbit), conic(goal));
+
+    //the radii are equal where a cos θ + b sin θ = c
+    let a = p1 * e2 * w2.cos() - p2 * e1 * w1.cos();
+    let b = p1 * e2 * w2.sin() - p2 * e1 * w1.sin();
+    let c = p2 - p1;
+    let amplitude = a.hypot(b);
+    let spread = match amplitude > 0.0 {
+        true => (c / amplitude).clamp(-1.0, 1.0).acos(),
+        false => 0.0,
+    };
+
+    [b.atan2(a) - spread, b.atan2(a) + spread]
+}
+
+//the cheapest transfer from here to the target around the ship's reference body: the burn (m/s, in the world)
+//onto it, and the step that stops at the other end
+fn intercept(ephemeris: &Ephemeris, ship: &Ship, target: Entity) -> Result<(DVec2, Step), &'static str> {
+    let reference = ship.reference.ok_or("not orbiting anything")?;
+    if reference == target {
+        return Err("already orbiting the target");
+    }
+
+    let t = ship.time;
+    let mu = G * ephemeris.mass(reference);
+    let relative = |t: f64| -> Option<(DVec2, DVec2)> {
+        let (position, velocity) = ephemeris.state_at(target, t)?;
+        let (centre, centre_velocity) = ephemeris.state_at(reference, t)?;
+        Some((position - centre, velocity - centre_velocity))
+    };
+
+    let (centre, centre_velocity) = ephemeris.state_at(reference, t).ok_or("not orbiting anything")?;
+    let (position, velocity) = (ship.position - centre, ship.velocity - centre_velocity);
+    if relative(t).is_none() {
+        return Err("target is gone");
+    }
+
+    let (departure, flight_time, arrival) = cheapest_transfer(mu, t, position, velocity, relative)
+        .ok_or("no transfer to the target")?;
+    Ok((departure, Step::Arrive { target, time: t + flight_time, delta_v: arrival }))
+}
+
+//cheapest transfer at time t from this position (m) and velocity (m/s) to a target at target(t), everything relative
+//to a body with gravitational parameter mu (m³/s²): the departure burn (m/s), the flight time (s), and the
+//delta-v (m/s) to match the target's velocity at the other end
+fn cheapest_transfer(
+    mu: f64, t: f64, position: DVec2, velocity: DVec2, target: impl Fn(f64) -> Option<(DVec2, DVec2)>
+) -> Option<(DVec2, f64, f64)> {
+    let (target_now, _) = target(t)?;
+    let flight_time = hohmann(mu, position.length(), target_now.length()).flight_time;
+    let (first, last) = (RENDEZVOUS_TIME_RANGE.0 * flight_time, RENDEZVOUS_TIME_RANGE.1 * flight_time);
+
+    (0..RENDEZVOUS_RESOLUTION)
+        .map(|i| first + (last - first) * i as f64 / (RENDEZVOUS_RESOLUTION - 1) as f64)
+        .filter_map(|flight_time| {
+            let (target_position, target_velocity) = target(t + flight_time)?;
+            let (leave, arrive) = lambert(mu, position, target_position, flight_time)?;
+            let arrival = (target_velocity - arrive).length();
+
+            let departure = leave - velocity;
+            Some((departure.length() + arrival, departure, flight_time, arrival))
+        })
+        .min_by(|(a, ..), (b, ..)| a.total_cmp(b))
+        .map(|(_, departure, flight_time, arrival)| (departure, flight_time, arrival))
+}
+
+fn log_autopilot(mut complete: EventReader<AutopilotComplete>, mut aborted: EventReader<AutopilotAborted>) {
+    for event in complete.read() {
+        info!("autopilot: {} complete", event.command.label());
+    }
+    for event in aborted.read() {
+        info!("autopilot: {} aborted, {}", event.command.label(), event.reason);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //gravitational parameter of the test primary
+    const MU: f64 = 1.0;
+
+    #[test]
+    fn apsis_burns_follow_vis_viva() {
+        //a circle needs nothing to stay one
+        assert!(apsis_speed_change(MU, 1.0, 1.0, 1.0).abs() < 1e-12);
+
+        //the first burn of a hohmann transfer out to 3, and the second at the far end
+        let transfer = hohmann(MU, 1.0, 3.0);
+        assert!((apsis_speed_change(MU, 1.0, 1.0, 3.0) - transfer.burns[0]).abs() < 1e-12);
+        assert!((apsis_speed_change(MU, 3.0, 2.0, 3.0) - transfer.burns[1]).abs() < 1e-12);
+
+        //and lowering the far side slows down
+        assert!(apsis_speed_change(MU, 3.0, 3.0, 1.0) < 0.0);
+    }
+
+    #[test]
+    fn crossings_of_coplanar_ellipses() {
+        //the same ellipse turned a quarter of the way round crosses it halfway between the periapses, both ways
+        let orbit = Orbit::from_elements(1.0, 0.5, 0.0, 0.0, 0.0, 0.0, 0.0);
+        let goal = Orbit::from_elements(1.0, 0.5, 0.0, 0.0, PI / 2.0, 0.0, 0.0);
+
+        let mut angles = crossings(&orbit, &goal).map(|angle| angle.rem_euclid(TAU));
+        angles.sort_by(f64::total_cmp);
+        assert!((angles[0] - PI / 4.0).abs() < 1e-9, "{angles:?}");
+        assert!((angles[1] - 5.0 * PI / 4.0).abs() < 1e-9, "{angles:?}");
+
+        //and a smaller ellipse pointing another way crosses wherever the radii agree
+        let goal = Orbit::from_elements(0.8, 0.3, 0.0, 0.0, 2.0, 0.0, 0.0);
+        for angle in crossings(&orbit, &goal) {
+            let radius = |orbit: &Orbit| orbit.radius_at(orbit.true_anomaly_towards(angle));
+            assert!((radius(&orbit) - radius(&goal)).abs() < 1e-9, "{} vs {}", radius(&orbit), radius(&goal));
+        }
+    }
+
+    #[test]
+    fn intercept_costs_about_a_hohmann_transfer() {
+        //a target out at 3, placed to arrive where a hohmann transfer from 1 would
+        let (r1, r2) = (1.0, 3.0);
+        let transfer = hohmann(MU, r1, r2);
+        let circle = Orbit::from_elements(r2, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        let n = circle.mean_motion(0.0, MU / G);
+        let target = |t: f64| {
+            let (position, velocity) = circle.state_at_time(t + PI / n - transfer.flight_time, 0.0, MU / G);
+            Some((position.truncate(), velocity.truncate()))
+        };
+
+        let (departure, flight_time, arrival) = cheapest_transfer(MU, 0.0, DVec2::X, DVec2::Y, target)
+            .expect("no transfer");
+        let delta_v = departure.length() + arrival;
+        assert!(delta_v >= transfer.delta_v() - 1e-9, "{delta_v} vs {}", transfer.delta_v());
+        assert!(delta_v < 1.05 * transfer.delta_v(), "{delta_v} vs {}", transfer.delta_v());
+        assert!((flight_time / transfer.flight_time - 1.0).abs() < 0.1);
+
+        //nowhere to be found
+        assert!(cheapest_transfer(MU, 0.0, DVec2::X, DVec2::Y, |_| None).is_none());
+    }
+}
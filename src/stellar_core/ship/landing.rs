@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+
+use crate::stellar_core;
+use stellar_core::clock::SimulationClock;
+use stellar_core::navigation::{Contact, ContactKind, collision::find_contact, integrator::State};
+use stellar_core::solar_system::Ephemeris;
+use stellar_core::ship::Ship;
+
+pub struct LandingPlugin;
+impl Plugin for LandingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<Touchdown>()
+            //after gravity and the engines have had their say, and before the orbit is refitted
+            .add_systems(FixedUpdate, resolve_contact
+                .after(super::update_ship)
+                .after(super::maneuver::execute_burns)
+                .before(super::update_osculating_orbit)
+            )
+            .add_systems(Update, log_touchdowns)
+            ;
+    }
+}
+
+///Fired when a ship reaches a body's surface, however it went.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Touchdown {
+    pub ship: Entity,
+    pub contact: Contact,
+}
+
+//catches the ship at the surface of whatever it ran into, and holds it there until it lifts off
+pub(super) fn resolve_contact(
+    time: Res<Time<Fixed>>,
+    clock: Res<SimulationClock>,
+    ephemeris: Res<Ephemeris>,
+    mut touchdowns: EventWriter<Touchdown>,
+    mut ship_query: Query<(Entity, &mut Ship, &mut Sprite)>
+) {
+    let Ok((entity, mut ship, mut sprite)) = ship_query.get_single_mut() else { return };
+
+    let dt = match clock.paused {
+        true => 0.0,
+        false => time.delta_secs_f64() * clock.warp,
+    };
+
+    let contact = match ship.contact {
+        Some(contact) => contact,
+        None => {
+            //the velocity hardly changes over one step, so the one at the end does for both ends
+            let from = State::new(ship.previous_position, ship.velocity);
+            let to = State::new(ship.position, ship.velocity);
+            let Some(contact) = find_contact(&ephemeris, from, to, ship.time - dt, dt) else { return };
+
+            touchdowns.send(Touchdown { ship: entity, contact });
+            if contact.kind == ContactKind::Crash {
+                ship.maneuvers.clear();
+                ship.burn = None;
+                sprite.color = Color::srgb(1.0, 0.3, 0.3);
+            }
+            ship.contact = Some(contact);
+            contact
+        },
+    };
+
+    let Some(body) = ephemeris.body(contact.body) else { return };
+    let Some((centre, surface_velocity)) = ephemeris.state_at(contact.body, ship.time) else { return };
+
+    //thrust that beat gravity carries the ship back off the surface. wrecks stay put
+    let distance = ship.position.distance(centre);
+    if distance > body.radius && contact.kind != ContactKind::Crash {
+        info!("{:?} lifted off {:?} at t = {:.0} s", entity, contact.body, ship.time);
+        ship.contact = None;
+        return;
+    }
+
+    //stand on the surface, keeping only motion straight up from it
+    let normal = (ship.position - centre).normalize_or(contact.normal);
+    let climb = match contact.kind {
+        ContactKind::Crash => 0.0,
+        _ => (ship.velocity - surface_velocity).dot(normal).max(0.0),
+    };
+
+    ship.position = centre + normal * body.radius;
+    ship.velocity = surface_velocity + normal * climb;
+    ship.velocity_changed();
+}
+
+fn log_touchdowns(mut touchdowns: EventReader<Touchdown>) {
+    for touchdown in touchdowns.read() {
+        let contact = touchdown.contact;
+        info!(
+            "{:?}: {} on {:?} at {:.1} m/s, t = {:.0} s",
+            touchdown.ship, contact.kind.label(), contact.body, contact.speed, contact.time
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+use bevy::math::DVec2;
+
+use crate::stellar_core;
+use stellar_core::clock::SimulationClock;
+use stellar_core::floating_origin::FloatingOrigin;
+use stellar_core::navigation::{ManeuverNode, integrator::State};
+use stellar_core::navigation::maneuver::{local_frame, BURN_ACCELERATION};
+use stellar_core::solar_system::{Ephemeris, to_meters};
+use stellar_core::ship::Ship;
+
+//delta-v (m/s) a handle adds per second it's held, ten times that with shift
+const HANDLE_RATE: f64 = 10.0;
+//how many fixed steps of warp ahead of a burn the clock starts slowing down
+pub(super) const WARP_LEAD_STEPS: f64 = 20.0;
+//length of the handle arrows, in pixels
+const HANDLE_LENGTH: f32 = 25.0;
+
+pub struct ManeuverPlugin;
+impl Plugin for ManeuverPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<SelectedNode>()
+            .add_systems(Update, (place_node, edit_node).chain())
+            //burns go on top of the step gravity has just taken
+            .add_systems(FixedUpdate, execute_burns.after(super::update_ship).before(super::update_osculating_orbit))
+            .add_systems(PostUpdate, draw_nodes.after(FloatingOrigin::recentre))
+            ;
+    }
+}
+
+///Index of the maneuver node the handles act on.
+#[derive(Resource, Debug, Default)]
+pub struct SelectedNode(pub Option<usize>);
+
+//N drops a node on the predicted point closest to the cursor
+fn place_node(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    origin: Res<FloatingOrigin>,
+    mut selected: ResMut<SelectedNode>,
+    mut ship_query: Query<&mut Ship>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    q_windows: Query<&Window, With<bevy::window::PrimaryWindow>>
+) {
+    if !keyboard.just_pressed(KeyCode::KeyN) {
+        return;
+    }
+
+    let Ok(mut ship) = ship_query.get_single_mut() else { return };
+    let Ok((camera, camera_transform)) = camera_query.get_single() else { return };
+    let Ok(window) = q_windows.get_single() else { return };
+    let Some(cursor_pos) = window.cursor_position() else { return };
+    let Ok(cursor_pos) = camera.viewport_to_world_2d(camera_transform, cursor_pos) else { return };
+
+    let cursor = origin.position + to_meters(cursor_pos);
+    let closest = ship.prediction.points.iter().zip(&ship.prediction.times)
+        .filter(|(_, time)| **time > ship.time)
+        .min_by(|(a, _), (b, _)| a.distance_squared(cursor).total_cmp(&b.distance_squared(cursor)));
+    let Some((_, &time)) = closest else { return };
+
+    let i = ship.maneuvers.partition_point(|node| node.time < time);
+    ship.maneuvers.insert(i, ManeuverNode::new(time));
+    ship.prediction_stale = true;
+    selected.0 = Some(i);
+}
+
+//tab picks a node, W/S push prograde/retrograde, D/A radial out/in, delete drops it
+fn edit_node(
+    time: Res<Time>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut selected: ResMut<SelectedNode>,
+    mut ship_query: Query<&mut Ship>
+) {
+    let Ok(mut ship) = ship_query.get_single_mut() else { return };
+
+    if ship.maneuvers.is_empty() {
+        selected.0 = None;
+        return;
+    }
+
+    if keyboard.just_pressed(KeyCode::Tab) {
+        selected.0 = match selected.0 {
+            Some(i) if i + 1 < ship.maneuvers.len() => Some(i + 1),
+            _ => Some(0),
+        };
+    }
+
+    let Some(i) = selected.0.filter(|i| *i < ship.maneuvers.len()) else { return };
+
+    if keyboard.just_pressed(KeyCode::Delete) {
+        ship.maneuvers.remove(i);
+        ship.prediction_stale = true;
+        selected.0 = None;
+        return;
+    }
+
+    let axis = |positive: KeyCode, negative: KeyCode| {
+        keyboard.pressed(positive) as i32 as f64 - keyboard.pressed(negative) as i32 as f64
+    };
+    let (prograde, radial) = (axis(KeyCode::KeyW, KeyCode::KeyS), axis(KeyCode::KeyD, KeyCode::KeyA));
+    if prograde == 0.0 && radial == 0.0 {
+        return;
+    }
+
+    let rate = match keyboard.pressed(KeyCode::ShiftLeft) || keyboard.pressed(KeyCode::ShiftRight) {
+        true => HANDLE_RATE * 10.0,
+        false => HANDLE_RATE,
+    } * time.delta_secs_f64();
+
+    let node = &mut ship.maneuvers[i];
+    node.prograde += prograde * rate;
+    node.radial += radial * rate;
+    ship.prediction_stale = true;
+}
+
+//drops the warp on the way up to a node, lights the engine at the right moment, and burns until the delta-v is spent
+pub(super) fn execute_burns(
+    time: Res<Time<Fixed>>,
+    mut clock: ResMut<SimulationClock>,
+    ephemeris: Res<Ephemeris>,
+    mut selected: ResMut<SelectedNode>,
+    mut ship_query: Query<(&mut Ship, &mut Transform)>
+) {
+    let Ok((mut ship, mut transform)) = ship_query.get_single_mut() else { return };
+
+    let dt = match clock.paused {
+        true => 0.0,
+        false => time.delta_secs_f64() * clock.warp,
+    };
+
+    if let Some(node) = ship.maneuvers.first().copied() {
+        let lead = node.burn_start() - ship.time;
+
+        //come out of warp gradually so the burn isn't stepped over
+        if clock.warp > 1.0 && lead < clock.warp * time.delta_secs_f64() * WARP_LEAD_STEPS {
+            clock.step_warp(-1);
+        }
+
+        if ship.burn.is_none() && lead <= 0.0 {
+            ship.maneuvers.remove(0);
+            selected.0 = selected.0.and_then(|i| i.checked_sub(1));
+
+            let state = State::new(ship.position, ship.velocity);
+            let delta_v = node.world_delta_v(&ephemeris, state, ship.time);
+            ship.burn = (delta_v.length_squared() > 0.0).then_some(delta_v);
+            ship.prediction_stale = true;
+        }
+    }
+
+    let Some(remaining) = ship.burn else { return };
+
+    let delta_v = remaining.clamp_length_max(BURN_ACCELERATION * dt);
+    ship.velocity += delta_v;
+    ship.velocity_changed();
+
+    let remaining = remaining - delta_v;
+    ship.burn = (remaining.length() > 1e-6).then_some(remaining);
+
+    //point the main engine along the burn
+    ship.angular = 0.0;
+    transform.rotation = Quat::from_rotation_z(delta_v.y.atan2(delta_v.x) as f32);
+}
+
+//rings each node where the prediction reaches it, with arrows for prograde, radial and the burn itself
+fn draw_nodes(
+    mut gizmos: Gizmos,
+    ephemeris: Res<Ephemeris>,
+    origin: Res<FloatingOrigin>,
+    selected: Res<SelectedNode>,
+    ship: Query<&Ship>
+) {
+    let Ok(ship) = ship.get_single() else { return };
+
+    for (i, node) in ship.maneuvers.iter().enumerate() {
+        //nodes the prediction never reached have nowhere to be drawn
+        let Some((_, state)) = ship.prediction.burns.iter().find(|(time, _)| *time == node.time) else { continue };
+
+        let centre = origin.to_render(state.position);
+        let color = match selected.0 == Some(i) {
+            true => Color::srgb(1.0, 0.6, 0.1),
+            false => Color::srgb(0.6, 0.6, 0.6),
+        };
+        gizmos.circle_2d(centre, 10.0, color);
+
+        let (prograde, radial) = local_frame(&ephemeris, *state, node.time);
+        gizmos.arrow_2d(centre, centre + prograde.as_vec2() * HANDLE_LENGTH, Color::srgb(0.3, 1.0, 0.3));
+        gizmos.arrow_2d(centre, centre + radial.as_vec2() * HANDLE_LENGTH, Color::srgb(0.3, 0.6, 1.0));
+
+        let burn = node.world_delta_v(&ephemeris, *state, node.time).normalize_or_zero();
+        if burn != DVec2::ZERO {
+            gizmos.arrow_2d(centre, centre + burn.as_vec2() * HANDLE_LENGTH * 1.6, color);
+        }
+    }
+}
@@ -1,67 +1,123 @@
 use bevy::prelude::*;
-use bevy_prototype_lyon::prelude::*;
+
+use std::f64::consts::TAU;
 
 use crate::stellar_core;
+use crate::stellar_core::navigation::{ContactKind, MarkerKind};
+use crate::stellar_core::solar_system::{Ephemeris, orbit::Conic};
+use crate::stellar_core::floating_origin::{FloatingOrigin, WorldPosition};
+
+//points along the drawn osculating conic
+const CONIC_SEGMENTS: usize = 128;
+//how far out an escape trajectory is drawn when nothing bounds it, in periapsis distances
+const MAX_CONIC_REACH: f64 = 50.0;
+
+//width of the predicted path, in pixels
+const PATH_WIDTH: f32 = 5.0;
 
-#[derive(Component)]
 pub struct ShipPath;
 
+//the predicted path gets its own gizmo group so it can be drawn thicker than the rest
+#[derive(Default, Reflect, GizmoConfigGroup)]
+struct PathGizmos;
+
 impl Plugin for ShipPath {
     fn build(&self, app: &mut App) {
         //set up the startup and update systems
         app
+            .init_gizmo_group::<PathGizmos>()
             .add_systems(Startup, ShipPath::new)
-            .add_systems(Update, ShipPath::update)
+            //drawn relative to the origin, so wait for it to move first
+            .add_systems(PostUpdate, (ShipPath::update, ShipPath::draw_markers, ShipPath::draw_osculating).after(FloatingOrigin::recentre))
         ;
     }
 }
 
 impl ShipPath {
-    pub fn new(mut commands: Commands) {
-        //dummy path at first
-        let path_points = vec![Vec2::ZERO];
-
-        ShipPath::spawn_path(&mut commands, &path_points);
+    pub fn new(mut config_store: ResMut<GizmoConfigStore>) {
+        let (config, _) = config_store.config_mut::<PathGizmos>();
+        config.line_width = PATH_WIDTH;
     }
 
-    fn spawn_path(commands: &mut Commands, points: &[Vec2]) {
+    //one linestrip through the upcoming prediction, fading out towards the end. immediate mode,
+    //so nothing is spawned or despawned when the prediction changes
+    fn update(
+        mut gizmos: Gizmos<PathGizmos>,
+        ship: Query<(&stellar_core::ship::Ship, &WorldPosition)>,
+        origin: Res<FloatingOrigin>
+    ) {
+        let Ok((ship, position)) = ship.get_single() else { return };
+
+        //the prediction is reused while the ship coasts, so skip what it has already flown through
+        let prediction = &ship.prediction;
+        let upcoming = prediction.points.iter().zip(&prediction.times)
+            .filter(|(_, time)| **time > ship.time)
+            .map(|(point, _)| *point);
+        let points: Vec<Vec2> = std::iter::once(**position).chain(upcoming)
+            .map(|point| origin.to_render(point))
+            .collect();
+
         let segment_count = points.len().saturating_sub(1);
-    
-        //iterate through all the segments to generate the fade-out effect
-        for (i, window) in points.windows(2).enumerate() {
-            let start = window[0];
-            let end = window[1];
-    
-            let mut path_builder = PathBuilder::new();
-            path_builder.move_to(start);
-            path_builder.line_to(end);
-            let path = path_builder.build();
-    
+        gizmos.linestrip_gradient_2d(points.into_iter().enumerate().map(|(i, point)| {
             let t = i as f32 / segment_count.max(1) as f32; //normalized [0,1]
             let alpha = 1.0 - t.powf(1.5); //nonlinear fade
-    
-            commands.spawn((
-                ShapeBundle { path, ..default() },
-                Stroke::new(
-                    Color::linear_rgba(0.0, 0.88, 1.0, alpha), //cyan fade
-                    5.0,
-                ),
-                ShipPath,
-            ));
-        }
+            (point, Color::linear_rgba(0.0, 0.88, 1.0, alpha)) //cyan fade
+        }));
     }
 
-    fn update(
-        mut commands: Commands,
-        path: Query<Entity, (With<ShipPath>, Without<stellar_core::ship::Ship>)>,
-        ship: Query<&stellar_core::ship::Ship>
+    //rings the upcoming events along the path
+    fn draw_markers(
+        mut gizmos: Gizmos,
+        ship: Query<&stellar_core::ship::Ship>,
+        origin: Res<FloatingOrigin>
     ) {
-        //despawn old path entity
-        for entity in &path {
-            commands.entity(entity).despawn_recursive();
+        let Ok(ship) = ship.get_single() else { return };
+
+        for marker in ship.prediction.markers.iter().filter(|marker| marker.time > ship.time) {
+            let color = match marker.kind {
+                MarkerKind::Periapsis | MarkerKind::Apoapsis => Color::srgb(1.0, 1.0, 1.0),
+                MarkerKind::ClosestApproach => Color::srgb(0.3, 1.0, 0.3),
+                MarkerKind::SoiEntry | MarkerKind::SoiExit => Color::srgb(1.0, 0.85, 0.2),
+                MarkerKind::Impact => match ship.prediction.impact.map(|contact| contact.kind) {
+                    Some(ContactKind::SoftLanding) => Color::srgb(0.3, 1.0, 0.3),
+                    Some(ContactKind::HardLanding) => Color::srgb(1.0, 0.6, 0.1),
+                    _ => Color::srgb(1.0, 0.2, 0.2),
+                },
+            };
+
+            gizmos.circle_2d(origin.to_render(marker.position), 8.0, color);
         }
-    
-        //spawn the new path
-        ShipPath::spawn_path(&mut commands, &ship.single().future_path);
+    }
+
+    //the ideal conic around the reference body, faint next to the predicted path
+    fn draw_osculating(
+        mut gizmos: Gizmos,
+        ephemeris: Res<Ephemeris>,
+        ship: Query<&stellar_core::ship::Ship>,
+        origin: Res<FloatingOrigin>
+    ) {
+        let Ok(ship) = ship.get_single() else { return };
+        let Some(patch) = &ship.osculating else { return };
+        let Some(centre) = ephemeris.position_at(patch.reference, ship.time) else { return };
+        let orbit = &patch.orbit;
+
+        //escape trajectories are cut off where they leave the sphere of influence
+        let (start, end) = match orbit.conic() {
+            Conic::Ellipse => (0.0, TAU),
+            _ => {
+                let soi = ephemeris.body(patch.reference).map_or(f64::INFINITY, |body| body.soi);
+                let reach = soi.min(orbit.periapsis * MAX_CONIC_REACH);
+                let limit = orbit.true_anomaly_limit().unwrap_or(0.0);
+                let cos = ((orbit.semi_latus() / reach - 1.0) / orbit.eccentricity).clamp(-1.0, 1.0);
+                let edge = cos.acos().min(limit * 0.999);
+                (-edge, edge)
+            },
+        };
+
+        let points = (0..=CONIC_SEGMENTS).map(|i| {
+            let true_anomaly = start + (end - start) * i as f64 / CONIC_SEGMENTS as f64;
+            origin.to_render(centre + orbit.position_at_true_anomaly(true_anomaly).truncate())
+        });
+        gizmos.linestrip_2d(points, Color::srgba(1.0, 1.0, 1.0, 0.3));
     }
 }
\ No newline at end of file
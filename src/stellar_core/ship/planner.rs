@@ -0,0 +1,377 @@
+use std::f64::consts::{PI, TAU};
+
+use bevy::prelude::*;
+
+use crate::stellar_core;
+use crate::procedural_generation::gen_icon::image_to_handle;
+use stellar_core::clock::SimulationClock;
+use stellar_core::navigation::{ManeuverNode, PorkchopGrid, PredictionConfig, Trajectory, Transfer, TransferRequest, integrator::State};
+use stellar_core::navigation::flyby::FlybySearchTask;
+use stellar_core::navigation::maneuver::local_frame;
+use stellar_core::navigation::transfer::{bi_elliptic, hohmann, next_window, phase_angle};
+use stellar_core::solar_system::{Ephemeris, Orbit, orbit::G};
+use stellar_core::ship::Ship;
+use stellar_core::ship::maneuver::SelectedNode;
+
+//departures and flight times along each side of the porkchop grid
+const GRID_RESOLUTION: usize = 48;
+//flight times on the grid, as fractions of the hohmann flight time
+const FLIGHT_TIME_RANGE: (f64, f64) = (0.3, 1.7);
+//departures on the grid never run further ahead than this many origin orbits
+const MAX_DEPARTURE_ORBITS: f64 = 4.0;
+//departures and flight times per leg in the flyby search. every extra leg multiplies the work by this much
+const FLYBY_RESOLUTION: usize = 12;
+//longest run of flybys the search tries
+const MAX_FLYBYS: usize = 2;
+//flyby burns smaller than this (m/s) are left off the committed nodes
+const MIN_FLYBY_BURN: f64 = 1.0;
+//passes lining the ejection burn up with the radius of the parking orbit where it happens
+const EJECTION_ITERATIONS: usize = 4;
+//keys that commit the first few flyby candidates
+const COMMIT_KEYS: [KeyCode; 3] = [KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3];
+//bi-elliptic apoapsis, as a multiple of the larger of the two orbits
+const BI_ELLIPTIC_REACH: f64 = 3.0;
+//where the J key writes the grid
+const EXPORT_CSV: &str = "porkchop.csv";
+const EXPORT_PNG: &str = "porkchop.png";
+
+pub struct PlannerPlugin;
+impl Plugin for PlannerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<TransferPlanner>()
+            .add_systems(Update, (planner_controls, update_planner, search_flybys, commit_trajectory).chain())
+            ;
+    }
+}
+
+///Transfer from wherever the ship is going round to the target, worked out from circular orbits.
+#[derive(Clone, Debug)]
+pub struct TransferPlan {
+    pub destination: Entity,
+    pub hohmann: Transfer,
+    pub bi_elliptic: Transfer,
+    ///How far ahead (rad) the destination has to be at departure for the Hohmann transfer.
+    pub phase: f64,
+    ///How far ahead (rad) it is now, between -π and π.
+    pub current_phase: f64,
+    ///Seconds until the phase is right. None if it never will be.
+    pub window: Option<f64>,
+}
+
+///The transfer planner: estimates for reaching the target, and a porkchop plot of the real transfers.
+#[derive(Resource, Default)]
+pub struct TransferPlanner {
+    pub open: bool,
+    pub plan: Option<TransferPlan>,
+    pub grid: Option<PorkchopGrid>,
+    ///The grid drawn as a picture, for the UI.
+    pub image: Option<Handle<Image>>,
+    ///Candidate trajectories from the last flyby search, cheapest first.
+    pub trajectories: Vec<Trajectory>,
+    //origin and destination the grid was worked out for
+    computed_for: Option<(Entity, Entity)>,
+    search: Option<FlybySearchTask>,
+}
+
+impl TransferPlanner {
+    ///True while a flyby search is running.
+    pub fn searching(&self) -> bool {
+        self.search.is_some()
+    }
+}
+
+//H opens and closes the planner, J writes the porkchop grid out as CSV and PNG
+fn planner_controls(keyboard: Res<ButtonInput<KeyCode>>, mut planner: ResMut<TransferPlanner>) {
+    if keyboard.just_pressed(KeyCode::KeyH) {
+        planner.open = !planner.open;
+        //always start from a fresh grid
+        planner.computed_for = None;
+    }
+
+    if !keyboard.just_pressed(KeyCode::KeyJ) {
+        return;
+    }
+    let Some(grid) = &planner.grid else {
+        info!("no porkchop plot to export");
+        return;
+    };
+
+    match std::fs::write(EXPORT_CSV, grid.to_csv()) {
+        Ok(()) => info!("porkchop plot written to {EXPORT_CSV}"),
+        Err(error) => error!("couldn't write {EXPORT_CSV}: {error}"),
+    }
+    match grid.to_image().save(EXPORT_PNG) {
+        Ok(()) => info!("porkchop plot written to {EXPORT_PNG}"),
+        Err(error) => error!("couldn't write {EXPORT_PNG}: {error}"),
+    }
+}
+
+//the estimates are cheap, so they follow the clock. the grid is only redone when the target changes,
+//or once the clock has run past its first departure step
+fn update_planner(
+    clock: Res<SimulationClock>,
+    ephemeris: Res<Ephemeris>,
+    config: Res<PredictionConfig>,
+    mut planner: ResMut<TransferPlanner>,
+    mut images: ResMut<Assets<Image>>,
+    ship_query: Query<&Ship>
+) {
+    if !planner.open {
+        return;
+    }
+
+    let Ok(ship) = ship_query.get_single() else { return };
+    let now = clock.time();
+
+    let origin = config.target.and_then(|destination| {
+        let (origin, origin_mass, key) = origin_orbit(&ephemeris, ship, destination)?;
+        Some((origin, origin_mass, key, destination))
+    });
+    let Some((origin, origin_mass, key, destination)) = origin else {
+        planner.plan = None;
+        planner.grid = None;
+        planner.computed_for = None;
+        if let Some(image) = planner.image.take() {
+            images.remove(&image);
+        }
+        return;
+    };
+
+    let Some(plan) = plan_transfer(&ephemeris, &origin, origin_mass, destination, now) else { return };
+
+    let stale = match (&planner.grid, planner.computed_for) {
+        (Some(grid), Some(computed_for)) => {
+            let step = grid.departures.get(1).zip(grid.departures.first()).map_or(0.0, |(b, a)| b - a);
+            computed_for != key || now - grid.departures[0] > step
+        },
+        _ => true,
+    };
+
+    if stale {
+        let span = departure_span(&ephemeris, &origin, origin_mass, destination);
+        let flight_time = plan.hohmann.flight_time;
+
+        let request = TransferRequest {
+            parent: origin.parent, origin: origin.clone(), origin_mass, destination, departures: (now, now + span)
+        };
+        planner.grid = PorkchopGrid::new(
+            &ephemeris, &request,
+            (FLIGHT_TIME_RANGE.0 * flight_time, FLIGHT_TIME_RANGE.1 * flight_time),
+            GRID_RESOLUTION
+        );
+        planner.computed_for = Some(key);
+
+        if let Some(image) = planner.image.take() {
+            images.remove(&image);
+        }
+        planner.image = planner.grid.as_ref().map(|grid| image_to_handle(grid.to_image(), &mut images));
+    }
+
+    planner.plan = Some(plan);
+}
+
+//K searches for flybys on the way to the target in the background, and picks up the results when they're in
+fn search_flybys(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    clock: Res<SimulationClock>,
+    ephemeris: Res<Ephemeris>,
+    config: Res<PredictionConfig>,
+    mut planner: ResMut<TransferPlanner>,
+    ship_query: Query<&Ship>
+) {
+    if let Some(trajectories) = planner.search.as_mut().and_then(|task| task.poll()) {
+        info!("flyby search found {} trajectories", trajectories.len());
+        planner.trajectories = trajectories;
+        planner.search = None;
+    }
+
+    if !planner.open || !keyboard.just_pressed(KeyCode::KeyK) || planner.searching() {
+        return;
+    }
+    let Ok(ship) = ship_query.get_single() else { return };
+    let Some(destination) = config.target else { return };
+    let Some((origin, origin_mass, _)) = origin_orbit(&ephemeris, ship, destination) else {
+        info!("no flyby search: the target doesn't go round the same body as the ship");
+        return;
+    };
+
+    let parent = origin.parent;
+    let via = ephemeris.iter()
+        .filter(|(body, _)| ephemeris.parent(*body) == Some(parent))
+        .map(|(body, _)| body)
+        .collect();
+    let now = clock.time();
+    let span = departure_span(&ephemeris, &origin, origin_mass, destination);
+
+    let request = TransferRequest { parent, origin, origin_mass, destination, departures: (now, now + span) };
+    planner.search = Some(FlybySearchTask::spawn(ephemeris.clone(), request, via, MAX_FLYBYS, FLYBY_RESOLUTION));
+    planner.trajectories.clear();
+}
+
+//the number keys swap the planned burns for those of a flyby candidate
+fn commit_trajectory(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    ephemeris: Res<Ephemeris>,
+    planner: Res<TransferPlanner>,
+    mut selected: ResMut<SelectedNode>,
+    mut ship_query: Query<&mut Ship>
+) {
+    if !planner.open {
+        return;
+    }
+    let Some(i) = COMMIT_KEYS.iter().position(|key| keyboard.just_pressed(*key)) else { return };
+    let Some(trajectory) = planner.trajectories.get(i) else { return };
+    let Ok(mut ship) = ship_query.get_single_mut() else { return };
+    let Some(destination) = trajectory.bodies.last().copied() else { return };
+    let Some((origin, origin_mass, _)) = origin_orbit(&ephemeris, &ship, destination) else { return };
+
+    let Some(nodes) = trajectory_nodes(&ephemeris, &ship, &origin, origin_mass, trajectory) else {
+        info!("can't commit that trajectory: the ship isn't in a closed parking orbit to leave from");
+        return;
+    };
+    info!("committed {} burns for {:.0} m/s", nodes.len(), trajectory.delta_v());
+    ship.maneuvers = nodes;
+    ship.prediction_stale = true;
+    selected.0 = None;
+}
+
+//the trajectory's burns as maneuver nodes. leaving the ship's own orbit, the departure burn is exactly the one
+//worked out. leaving a body's orbit it's the burn from the parking orbit that gives the same excess velocity,
+//see ejection_node. flyby and arrival burns go along the track
+fn trajectory_nodes(
+    ephemeris: &Ephemeris, ship: &Ship, origin: &Orbit, origin_mass: f64, trajectory: &Trajectory
+) -> Option<Vec<ManeuverNode>> {
+    let parent = origin.parent;
+    let departure = trajectory.departure;
+    let mut nodes = Vec::new();
+
+    let node = match ship.reference? == parent {
+        true => {
+            let (position, velocity) = origin.state_at_time(departure, origin_mass, ephemeris.mass(parent));
+            let (centre, centre_velocity) = ephemeris.state_at(parent, departure)?;
+            let state = State::new(centre + position.truncate(), centre_velocity + velocity.truncate());
+            let (prograde, radial) = local_frame(ephemeris, state, departure);
+
+            ManeuverNode {
+                prograde: trajectory.departure_burn.dot(prograde),
+                radial: trajectory.departure_burn.dot(radial),
+                ..ManeuverNode::new(departure)
+            }
+        },
+        false => ejection_node(ephemeris, ship, trajectory)?,
+    };
+    nodes.push(node);
+
+    for flyby in trajectory.flybys.iter().filter(|flyby| flyby.burn.abs() >= MIN_FLYBY_BURN) {
+        nodes.push(ManeuverNode { prograde: flyby.burn, ..ManeuverNode::new(flyby.time) });
+    }
+    nodes.push(ManeuverNode { prograde: -trajectory.arrival_burn, ..ManeuverNode::new(trajectory.arrival) });
+
+    Some(nodes)
+}
+
+//prograde burn off the ship's parking orbit round the origin body onto the escape hyperbola whose outgoing
+//asymptote points along the departure burn. the hyperbola leaves at θ∞ = acos(-1/e) past its periapsis, so the
+//burn goes where the ship is that far round behind the asymptote, in the parking orbit passed closest to the
+//departure. the radius there sets e, so the two are worked out together. None unless the ship is in a closed orbit
+fn ejection_node(ephemeris: &Ephemeris, ship: &Ship, trajectory: &Trajectory) -> Option<ManeuverNode> {
+    let reference = ship.reference?;
+    let parking = &ship.osculating.as_ref()?.orbit;
+    if !parking.is_bound() {
+        return None;
+    }
+
+    let body_mass = ephemeris.mass(reference);
+    let mu = G * body_mass;
+    let v_infinity = trajectory.departure_burn.length();
+    let asymptote = trajectory.departure_burn.y.atan2(trajectory.departure_burn.x);
+
+    //an inclination of π turns the orbit round the other way
+    let direction = parking.inclination.cos().signum();
+    let from = ship.time.max(trajectory.departure - parking.period(0.0, body_mass) / 2.0);
+
+    let mut radius = parking.semi_major_axis();
+    let mut time = trajectory.departure;
+    for _ in 0..EJECTION_ITERATIONS {
+        let eccentricity = 1.0 + radius * v_infinity.powi(2) / mu;
+        let ejection = (-1.0 / eccentricity).acos();
+
+        let true_anomaly = parking.true_anomaly_towards(asymptote - direction * ejection);
+        time = from + parking.time_to_true_anomaly(from, true_anomaly, 0.0, body_mass)?;
+        radius = parking.state_at_time(time, 0.0, body_mass).0.length();
+    }
+
+    let (_, velocity) = parking.state_at_time(time, 0.0, body_mass);
+    let prograde = (v_infinity.powi(2) + 2.0 * mu / radius).sqrt() - velocity.length();
+
+    Some(ManeuverNode { prograde, ..ManeuverNode::new(time) })
+}
+
+//how far ahead departures are looked for. one synodic period covers every lineup there is,
+//but two bodies going round together take forever
+fn departure_span(ephemeris: &Ephemeris, origin: &Orbit, origin_mass: f64, destination: Entity) -> f64 {
+    let parent_mass = ephemeris.mass(origin.parent);
+    let n1 = origin.mean_motion(origin_mass, parent_mass);
+    let n2 = ephemeris.body(destination)
+        .and_then(|body| body.orbit.as_ref())
+        .map_or(0.0, |orbit| orbit.mean_motion(ephemeris.mass(destination), parent_mass));
+
+    match n2 != n1 {
+        true => TAU / (n2 - n1).abs(),
+        false => f64::INFINITY,
+    }.min(MAX_DEPARTURE_ORBITS * TAU / n1)
+}
+
+//the orbit to leave from, its mass, and what the grid is keyed on. the ship's own orbit if it goes round
+//the same body as the destination, otherwise the orbit of the body it's near, if that one does
+fn origin_orbit(ephemeris: &Ephemeris, ship: &Ship, destination: Entity) -> Option<(Orbit, f64, (Entity, Entity))> {
+    let parent = ephemeris.parent(destination)?;
+    let reference = ship.reference?;
+
+    if reference == parent {
+        let mut orbit = ship.osculating.as_ref()?.orbit.clone();
+        orbit.parent = parent;
+        return orbit.is_bound().then_some((orbit, 0.0, (reference, destination)));
+    }
+
+    if ephemeris.parent(reference)? == parent && reference != destination {
+        let orbit = ephemeris.body(reference)?.orbit.clone()?;
+        return Some((orbit, ephemeris.mass(reference), (reference, destination)));
+    }
+
+    None
+}
+
+//hohmann and bi-elliptic between the two orbits as if they were circles at their semi-major axes
+fn plan_transfer(ephemeris: &Ephemeris, origin: &Orbit, origin_mass: f64, destination: Entity, t: f64) -> Option<TransferPlan> {
+    let parent = origin.parent;
+    let parent_mass = ephemeris.mass(parent);
+    let target = ephemeris.body(destination)?.orbit.as_ref()?;
+    let mu = G * parent_mass;
+
+    let (r1, r2) = (origin.semi_major_axis(), target.semi_major_axis());
+    if !(r1 > 0.0 && r2 > 0.0) {
+        return None;
+    }
+
+    let (position, _) = origin.state_at_time(t, origin_mass, parent_mass);
+    let (destination_position, _) = ephemeris.state_at(destination, t)?;
+    let (centre, _) = ephemeris.state_at(parent, t)?;
+
+    let angle = |p: bevy::math::DVec2| p.y.atan2(p.x);
+    let current_phase = (angle(destination_position - centre) - angle(position.truncate()) + PI).rem_euclid(TAU) - PI;
+    let phase = phase_angle(r1, r2);
+
+    let n1 = origin.mean_motion(origin_mass, parent_mass);
+    let n2 = target.mean_motion(ephemeris.mass(destination), parent_mass);
+
+    Some(TransferPlan {
+        destination,
+        hohmann: hohmann(mu, r1, r2),
+        bi_elliptic: bi_elliptic(mu, r1, r2, BI_ELLIPTIC_REACH * r1.max(r2)),
+        phase,
+        current_phase,
+        window: next_window(current_phase, phase, n1, n2),
+    })
+}
@@ -0,0 +1,99 @@
+use std::f64::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+
+use crate::stellar_core;
+use stellar_core::clock::SimulationClock;
+use stellar_core::floating_origin::{FloatingOrigin, WorldPosition};
+use stellar_core::navigation::{SolarSail, integrator::State};
+use stellar_core::navigation::radiation::{radiation_acceleration, sail_normal};
+use stellar_core::solar_system::Ephemeris;
+use stellar_core::ship::Ship;
+
+//how far each press of a bracket turns the sail, in radians
+const SAIL_STEP: f64 = 5.0 * std::f64::consts::PI / 180.0;
+//length of the drawn sail, in pixels
+const SAIL_LENGTH: f32 = 30.0;
+
+pub struct SailPlugin;
+impl Plugin for SailPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, sail_controls)
+            .add_systems(FixedUpdate, update_radiation_pressure
+                .after(super::update_ship)
+                .before(super::landing::resolve_contact)
+                .before(super::update_osculating_orbit)
+            )
+            .add_systems(PostUpdate, draw_sail.after(FloatingOrigin::recentre))
+            ;
+    }
+}
+
+//L deploys and stows the sail, the square brackets turn it
+fn sail_controls(keyboard: Res<ButtonInput<KeyCode>>, mut ship_query: Query<&mut Ship>) {
+    let Ok(mut ship) = ship_query.get_single_mut() else { return };
+
+    if keyboard.just_pressed(KeyCode::KeyL) {
+        ship.airframe.sail = match ship.airframe.sail {
+            Some(_) => None,
+            None => Some(SolarSail::default()),
+        };
+        ship.prediction_stale = true;
+        info!("solar sail: {}", ship.airframe.sail.is_some());
+    }
+
+    let turn = keyboard.just_pressed(KeyCode::BracketRight) as i32 - keyboard.just_pressed(KeyCode::BracketLeft) as i32;
+    let Some(sail) = &mut ship.airframe.sail else { return };
+    if turn == 0 {
+        return;
+    }
+
+    sail.angle = (sail.angle + turn as f64 * SAIL_STEP).clamp(-FRAC_PI_2, FRAC_PI_2);
+    ship.prediction_stale = true;
+}
+
+//starlight on the hull and the sail. the prediction allows for it, so it doesn't go stale
+fn update_radiation_pressure(
+    time: Res<Time<Fixed>>,
+    clock: Res<SimulationClock>,
+    ephemeris: Res<Ephemeris>,
+    mut ship_query: Query<&mut Ship>
+) {
+    let Ok(mut ship) = ship_query.get_single_mut() else { return };
+
+    //conics leave the light on the hull out, but a sail is thrust
+    if ship.contact.is_some() || (ship.patched_conics && ship.airframe.sail.is_none()) {
+        return;
+    }
+
+    let dt = match clock.paused {
+        true => 0.0,
+        false => time.delta_secs_f64() * clock.warp,
+    };
+
+    let state = State::new(ship.position, ship.velocity);
+    let acceleration = radiation_acceleration(&ephemeris, &ship.airframe, state, ship.time);
+    ship.velocity += acceleration * dt;
+    if ship.patched_conics {
+        ship.patch = None;
+    }
+}
+
+//a line across the ship, side on to the way the sail faces
+fn draw_sail(
+    mut gizmos: Gizmos,
+    ephemeris: Res<Ephemeris>,
+    origin: Res<FloatingOrigin>,
+    ship: Query<(&Ship, &WorldPosition)>
+) {
+    let Ok((ship, position)) = ship.get_single() else { return };
+    let Some(sail) = &ship.airframe.sail else { return };
+
+    let state = State::new(ship.position, ship.velocity);
+    let Some(normal) = sail_normal(&ephemeris, sail, state, ship.time) else { return };
+
+    let centre = origin.to_render(**position);
+    let edge = normal.perp().as_vec2() * SAIL_LENGTH / 2.0;
+    gizmos.line_2d(centre - edge, centre + edge, Color::srgb(0.9, 0.9, 1.0));
+}
@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use bevy::math::DVec2;
 
 pub mod orbit;
 pub use orbit::Orbit;
@@ -12,12 +13,64 @@ pub use celestial_body::{*, star::Luminosity};
 pub mod barycenter;
 pub use barycenter::Barycenter;
 
+pub mod ephemeris;
+pub use ephemeris::Ephemeris;
+
+pub mod atmosphere;
+pub use atmosphere::Atmosphere;
+
+pub mod lagrange;
+pub use lagrange::{Lagrange, LagrangePoint, HoveredLagrange};
+
+use crate::stellar_core::clock::SimulationClock;
+use crate::stellar_core::floating_origin::{FloatingOrigin, WorldPosition};
+use crate::stellar_utils::MTree;
+use crate::procedural_generation::gen_system::{gen_system, SystemConfig};
+use crate::stellar_utils::unit_conversion::*;
+
+///Meters of simulation space per world unit on screen.
+pub const METERS_PER_UNIT: f64 = 1.0e8;
+
+///Converts a position in meters to world units.
+pub fn to_world(meters: DVec2) -> Vec2 {
+    (meters / METERS_PER_UNIT).as_vec2()
+}
+
+///Converts a position in world units to meters.
+pub fn to_meters(world: Vec2) -> DVec2 {
+    world.as_dvec2() * METERS_PER_UNIT
+}
+
+///Seed the solar system is generated from.
+#[derive(Resource)]
+pub struct SystemSeed(pub String);
+
+impl Default for SystemSeed {
+    fn default() -> Self {
+        SystemSeed("eriku".to_string())
+    }
+}
+
 pub struct SolarSystemPlugin;
 impl Plugin for SolarSystemPlugin {
     fn build(&self, app: &mut App) {
         app
+            .init_resource::<SystemSeed>()
+            .init_resource::<SystemConfig>()
+            .init_resource::<Ephemeris>()
+            .init_resource::<HoveredLagrange>()
             .add_systems(Startup, setup_solar_system)
-            .add_systems(Update, update_solar_system);
+            .add_systems(PostStartup, Lagrange::spawn)
+            //rebuilt before the fixed timestep runs, so the ship always flies against this frame's bodies
+            .add_systems(PreUpdate, Ephemeris::update)
+            .add_systems(Update, (update_solar_system, Lagrange::hover))
+            .add_systems(Update, (
+                Star::update_evolution, 
+                Planet::update_temperature, 
+                Planet::update_protoplanets,
+            ).chain())
+            //transforms are only up to date once the origin has moved
+            .add_systems(PostUpdate, (Star::draw_disks, Star::draw_remnants, Planet::draw_atmospheres, Lagrange::draw).after(FloatingOrigin::recentre));
     }
 }
 
@@ -25,53 +78,85 @@ impl Plugin for SolarSystemPlugin {
 fn setup_solar_system(
     mut commands: Commands, 
     mut images: ResMut<Assets<Image>>,
+    seed: Res<SystemSeed>,
+    config: Res<SystemConfig>,
 ) {
+    let (system, report) = gen_system(&seed.0, &config);
+    info!("stability pass: {}", report);
 
-    commands.spawn(Star::get_bundle(
-        Star { radius: 1.0, spectral_type: "M".into() }, 
-        Mass {0: 10.0},
-        Luminosity {0: 1.0},
-        0.0, 
-        0.0, 
-        &mut images,
-    ));
-
-
-    // let system = crate::procedural_generation::gen_system::gen_system("eriku");
-
-    // let mut stars = vec![];
-    // let mut planets = vec![];
-
-    // for x in &system {
-    //     match x {
-    //         CelestialBody::Barycenter(_) => {},
-    //         CelestialBody::Star(s) => stars.push(s),
-    //         CelestialBody::Planet(p) => planets.push(p),
-    //     }
-    // }
-
-    // for (i, star) in stars.into_iter().enumerate() {
-    //     commands.spawn(Star::get_bundle(
-    //         star.clone(), 
-    //         i as f32 * 1000.0, 
-    //         10.0, 
-    //         &mut images
-    //     ));
-    // }
-
-    // for (i, planet) in planets.into_iter().enumerate() {
-    //     commands.spawn(Planet::get_bundle(
-    //         planet.clone(), 
-    //         i as f32 * 1000.0, 
-    //         1000.0, 
-    //         &mut images
-    //     ));
-    // }
-
-
+    spawn_body(&mut commands, &mut images, &system, system.root_id(), Entity::PLACEHOLDER, DVec2::ZERO, 0.0);
+    commands.insert_resource(report);
 }
 
-fn update_solar_system(_bodies: Query<&mut Planet>, mut _gizmos: Gizmos) {
-
+//spawns a node of the system tree and then its children, placing each body on its orbit around its parent.
+//parent position is in meters, parent mass is in kg
+fn spawn_body(
+    commands: &mut Commands,
+    images: &mut ResMut<Assets<Image>>,
+    system: &MTree<CelestialBody>,
+    id: u32,
+    parent: Entity,
+    parent_position: DVec2,
+    parent_mass: f64,
+) {
+    let Some(body) = system.get_value(id) else { return };
+
+    let (entity, position, mass) = match body {
+        CelestialBody::Star(star) => {
+            let render = to_world(parent_position);
+            let entity = commands.spawn((
+                Star::get_bundle(
+                    star.clone(),
+                    Mass(sols(star.mass)),
+                    star.luminosity(),
+                    render.x,
+                    render.y,
+                    images,
+                ),
+                WorldPosition(parent_position),
+            )).id();
+
+            (entity, parent_position, sols(star.mass))
+        },
+        CelestialBody::Planet(planet) => {
+            let mut planet = planet.clone();
+            planet.orbit.parent = parent;
+
+            let mass = earths(planet.mass);
+            let (x, y) = planet.orbit.position_at_time(0.0, mass, parent_mass);
+            let position = parent_position + DVec2::new(x, y);
+            let render = to_world(position);
+
+            let entity = commands.spawn((
+                Planet::get_bundle(planet, render.x, render.y, images),
+                Mass(mass),
+                WorldPosition(position),
+            )).id();
+
+            (entity, position, mass)
+        },
+        CelestialBody::Barycenter(_) => (parent, parent_position, parent_mass)
+    };
+
+    for child in system.children(id) {
+        spawn_body(commands, images, system, child, entity, position, mass);
+    }
 }
 
+///Moves planets and moons along their orbits around their parent entities, and the Lagrange points with them.
+fn update_solar_system(
+    time: Res<Time<Fixed>>,
+    clock: Res<SimulationClock>,
+    ephemeris: Res<Ephemeris>,
+    mut planets: Query<(Entity, &mut WorldPosition), Or<(With<Planet>, With<Lagrange>)>>,
+) {
+    //drawn between the last two fixed steps, the same as the ship
+    let t = clock.render_time(time.overstep_fraction_f64());
+
+    for (entity, mut position) in planets.iter_mut() {
+        //the ephemeris walks up the hierarchy, so moons follow the planet they orbit
+        if let Some(now) = ephemeris.position_at(entity, t) {
+            **position = now;
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,56 @@
+use crate::procedural_generation::gen_planet::EARTH_GRAVITY;
+
+//N.B.: SI units throughout, like orbit.rs.
+
+///Molar gas constant, J/(mol K).
+const GAS_CONSTANT: f64 = 8.314462618;
+///Pascals in one atmosphere.
+const ATMOSPHERE: f64 = 101_325.0;
+///Molar mass (kg/mol) of hydrogen and helium, for gas giants.
+const HYDROGEN_HELIUM: f64 = 2.3e-3;
+///Molar mass (kg/mol) of nitrogen and carbon dioxide rich air, for everything else.
+const HEAVY_AIR: f64 = 2.9e-2;
+///Density (kg/m³) at which the atmosphere is considered to end.
+const EDGE_DENSITY: f64 = 1e-8;
+
+///An isothermal atmosphere, whose density falls by a factor of e every scale height.
+#[derive(Clone, Copy, Debug)]
+pub struct Atmosphere {
+    ///Density at the surface, in kg/m³.
+    pub surface_density: f64,
+    ///Height over which the density falls by a factor of e, in meters.
+    pub scale_height: f64,
+}
+
+impl Atmosphere {
+    ///Atmosphere with this surface pressure (atm), temperature (K), surface gravity (g) and gas.
+    ///None when there's no air at all.
+    pub fn new(pressure: f64, temperature: f64, surface_gravity: f64, gas_giant: bool) -> Option<Atmosphere> {
+        if pressure <= 0.0 || temperature <= 0.0 || surface_gravity <= 0.0 {
+            return None;
+        }
+
+        let molar_mass = match gas_giant {
+            true => HYDROGEN_HELIUM,
+            false => HEAVY_AIR,
+        };
+
+        Some(Atmosphere {
+            surface_density: pressure * ATMOSPHERE * molar_mass / (GAS_CONSTANT * temperature),
+            scale_height: GAS_CONSTANT * temperature / (molar_mass * surface_gravity * EARTH_GRAVITY),
+        })
+    }
+
+    ///Density (kg/m³) at this altitude (m) above the surface.
+    pub fn density_at(&self, altitude: f64) -> f64 {
+        match altitude < self.height() {
+            true => self.surface_density * (-altitude.max(0.0) / self.scale_height).exp(),
+            false => 0.0,
+        }
+    }
+
+    ///Altitude (m) of the top of the atmosphere, where the air gets too thin to matter.
+    pub fn height(&self) -> f64 {
+        self.scale_height * (self.surface_density / EDGE_DENSITY).ln().max(0.0)
+    }
+}
@@ -6,5 +6,12 @@ pub use mass::Mass;
 pub mod star;
 pub use star::Star;
 
-#[derive(Component)]
-pub struct CelestialBody;
\ No newline at end of file
+use super::{Barycenter, Planet};
+
+///Any node in a generated system's MTree.
+#[derive(Debug)]
+pub enum CelestialBody {
+    Barycenter(Barycenter),
+    Star(Star),
+    Planet(Planet),
+}
\ No newline at end of file
@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 
+///Mass in kilograms, which is what gravity pulls with.
 #[derive(Component)]
 pub struct Mass(pub f64);
 
@@ -3,18 +3,118 @@ use bevy::prelude::*;
 pub mod luminosity;
 pub use luminosity::Luminosity;
 
+pub mod disk;
+pub use disk::CircumstellarDisk;
+
 use super::Mass;
 
-use crate::stellar_core::solar_system::Orbit;
+use crate::stellar_core::solar_system::{Orbit, METERS_PER_UNIT};
+use crate::stellar_core::clock::SimulationClock;
+use crate::stellar_utils::unit_conversion::*;
 use crate::procedural_generation::{self, gen_star as gen};
+use gen::{EvolutionTrack, Phase, Remnant, RemnantKind};
+
+///Smallest a black hole's rings are drawn, in pixels, so it can still be seen from far out.
+const MIN_RING_PIXELS: f32 = 2.0;
 
 #[derive(Clone, Component)]
 pub struct Star {
+    ///In solar radii.
     pub radius: f64,
     pub spectral_type: String,
+    ///Current mass in solar masses.
+    pub mass: f64,
+    ///Effective temperature in kelvin.
+    pub temperature: f64,
+    ///Age in GY since the protostar collapsed.
+    pub age: f64,
+    pub phase: Phase,
+    ///Magnetic activity relative to the present day sun.
+    pub activity: f64,
+    pub track: EvolutionTrack,
+    ///Present while the star is young and still feeding from its birth disk.
+    pub disk: Option<CircumstellarDisk>,
+    ///Set once the star has died.
+    pub remnant: Option<Remnant>,
+}
+
+impl std::fmt::Debug for Star {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(
+            format!("{} {:?} M(s): {:.2} R(s): {:.2} Temp: {:.0}K Age: {:.2}Gy",
+            self.spectral_type,
+            self.phase,
+            self.mass,
+            self.radius,
+            self.temperature,
+            self.age,
+        ).as_str())
+    }
 }
 
 impl Star {
+    ///Creates a star of this initial mass (solar masses), age (GY) and metallicity (solar units).
+    pub fn new(solar_mass: f64, age_gy: f64, metallicity: f64) -> Self {
+        let mut star = Star {
+            radius: 0.0,
+            spectral_type: String::new(),
+            mass: solar_mass,
+            temperature: 0.0,
+            age: age_gy,
+            phase: Phase::MainSequence,
+            activity: 1.0,
+            track: EvolutionTrack::new(solar_mass, metallicity),
+            disk: None,
+            remnant: None,
+        };
+        star.evolve(0.0);
+
+        star
+    }
+
+    ///Luminosity in solar units, derived from radius and temperature plus any accretion.
+    ///Black holes have no surface, so their accretion disk is what shines.
+    pub fn luminosity(&self) -> Luminosity {
+        if let Some(black_hole) = self.remnant.as_ref().and_then(|r| r.black_hole.as_ref()) {
+            return Luminosity(black_hole.disk.luminosity);
+        }
+
+        let accretion = match &self.disk {
+            Some(disk) => disk.accretion_luminosity(self.mass, self.radius),
+            None => 0.0
+        };
+
+        Luminosity(gen::evolution::luminosity_from(self.radius, self.temperature) + accretion)
+    }
+
+    ///True for T Tauri and Herbig Ae/Be stars that have not yet reached the main sequence.
+    pub fn is_young(&self) -> bool {
+        self.phase == Phase::PreMainSequence
+    }
+
+    ///Advances the star along its track by dt_gy and refreshes its properties.
+    pub fn evolve(&mut self, dt_gy: f64) {
+        self.age += dt_gy;
+
+        let state = self.track.state_at(self.age);
+        self.radius = state.radius;
+        self.spectral_type = state.spectral_type;
+        self.mass = state.mass;
+        self.temperature = state.temperature;
+        self.phase = state.phase;
+        self.activity = state.activity;
+        self.remnant = state.remnant;
+
+        self.disk = match self.phase {
+            Phase::Remnant => None,
+            _ => CircumstellarDisk::new(
+                self.mass,
+                gen::evolution::luminosity_from(self.radius, self.temperature),
+                self.age
+            )
+        };
+    }
+
     pub fn get_bundle(
         star: Self, mass: Mass, luminosity: Luminosity, 
         x: f32, y: f32, mut images: &mut ResMut<Assets<Image>>, 
@@ -22,6 +122,13 @@ impl Star {
         let radius = star.radius;
         let tex_size = (radius as u32 * 100).max(16);
 
+        let (r, g, b) = match star.remnant.as_ref().map(|r| r.kind) {
+            Some(RemnantKind::WhiteDwarf) => (220, 230, 255),
+            Some(RemnantKind::NeutronStar) => (140, 190, 255),
+            Some(RemnantKind::BlackHole) => (90, 40, 120),
+            None => (255, 225, 30),
+        };
+
         (
             star,
             mass,
@@ -29,7 +136,7 @@ impl Star {
             Sprite { 
                 image: procedural_generation::gen_icon::circle_texture(
                     tex_size, tex_size, &mut images,
-                    255, 225, 30, 255
+                    r, g, b, 255
                 ),
                 custom_size: Some(Vec2::splat((radius as f32 * 500.0).max(1000.0))),
                 ..default()
@@ -38,4 +145,89 @@ impl Star {
         )
 
     }
-}
\ No newline at end of file
+
+    ///Moves every star along its evolution track as time passes, at the clock's evolution scale.
+    ///Mass lost to winds and supernovae is carried over to the star's gravity.
+    pub fn update_evolution(
+        clock: Res<SimulationClock>,
+        mut stars: Query<(&mut Star, &mut Luminosity, &mut Mass)>
+    ) {
+        let dt_gy = to_gigayears(clock.evolution_delta_secs());
+
+        for (mut star, mut luminosity, mut mass) in stars.iter_mut() {
+            let previous_mass = star.mass;
+            star.evolve(dt_gy);
+
+            *luminosity = star.luminosity();
+            if star.mass != previous_mass {
+                **mass *= star.mass / previous_mass;
+            }
+        }
+    }
+
+    ///Draws circumstellar disks around young stars, with the frost line marked.
+    pub fn draw_disks(mut gizmos: Gizmos, stars: Query<(&Star, &Transform)>) {
+        for (star, transform) in stars.iter() {
+            let Some(disk) = &star.disk else { continue };
+
+            let center = transform.translation.xy();
+            //fade the whole disk out as it dissipates
+            let opacity = (disk.mass / (0.01 * star.mass)).sqrt() as f32;
+
+            let rings = 16;
+            for i in 0..=rings {
+                let r = disk.inner_radius
+                    * (disk.outer_radius / disk.inner_radius).powf(i as f64 / rings as f64);
+                let density = disk.relative_density(r).powf(0.25) as f32;
+
+                gizmos.circle_2d(
+                    center, (r / METERS_PER_UNIT) as f32,
+                    Color::srgba(0.8, 0.5, 0.3, density * opacity)
+                );
+            }
+
+            gizmos.circle_2d(
+                center, (disk.frost_line / METERS_PER_UNIT) as f32,
+                Color::srgba(0.5, 0.8, 1.0, opacity)
+            );
+        }
+    }
+
+    ///Draws event horizons and accretion disks around black holes at their true size.
+    ///The real horizon is a few km across, so each ring keeps a minimum size on screen, nested in order.
+    pub fn draw_remnants(
+        mut gizmos: Gizmos,
+        stars: Query<(&Star, &Transform)>,
+        camera_query: Query<&GlobalTransform, With<Camera2d>>
+    ) {
+        let pixel = camera_query.get_single().map_or(1.0, |transform| transform.compute_transform().scale.x);
+        //radius in meters on screen, or this many rings out from the smallest visible size
+        let radius = |meters: f64, ring: usize| {
+            ((meters / METERS_PER_UNIT) as f32).max(MIN_RING_PIXELS * (ring + 1) as f32 * pixel)
+        };
+
+        for (star, transform) in stars.iter() {
+            let Some(black_hole) = star.remnant.as_ref().and_then(|r| r.black_hole.as_ref())
+                else { continue };
+
+            let center = transform.translation.xy();
+            let disk = &black_hole.disk;
+
+            gizmos.circle_2d(center, radius(black_hole.event_horizon, 0), Color::BLACK);
+            gizmos.circle_2d(center, radius(black_hole.photon_sphere, 1), Color::srgb(0.6, 0.5, 0.8));
+
+            //rings spaced logarithmically out to the disk edge, coloured by local temperature
+            let rings = 12;
+            for i in 0..rings {
+                let r = disk.inner_radius
+                    * (disk.outer_radius / disk.inner_radius).powf(i as f64 / rings as f64) * 1.01;
+                let t = (disk.temperature_at(r, black_hole.mass) / disk.peak_temperature) as f32;
+
+                gizmos.circle_2d(
+                    center, radius(r, i + 2),
+                    Color::srgba(1.0, 0.3 + 0.7 * t, 0.1 + 0.9 * t * t, t.max(0.2))
+                );
+            }
+        }
+    }
+}
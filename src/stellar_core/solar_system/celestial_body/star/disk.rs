@@ -0,0 +1,58 @@
+use crate::stellar_utils::unit_conversion::*;
+
+///E-folding time of the disk mass in GY. Most disks are gone within ~10 million years.
+pub const DISK_LIFETIME: f64 = 0.002;
+
+///Gas and dust disk left over from star formation, feeding the young star and its protoplanets.
+#[derive(Clone, Debug)]
+pub struct CircumstellarDisk {
+    ///Dust sublimation radius in meters. Inside this everything is vapour.
+    pub inner_radius: f64,
+    ///In meters.
+    pub outer_radius: f64,
+    ///Water ice condenses beyond this radius (meters), which is where giant cores form.
+    pub frost_line: f64,
+    ///In solar masses.
+    pub mass: f64,
+    ///Solar masses per year falling onto the star.
+    pub accretion_rate: f64,
+}
+
+impl CircumstellarDisk {
+    ///Disk around a star of this mass (solar masses) and photospheric luminosity (solar units)
+    ///at this age (GY). Returns None once the disk has dissipated.
+    pub fn new(star_mass: f64, luminosity: f64, age_gy: f64) -> Option<Self> {
+        let initial_mass = 0.01 * star_mass;
+        let mass = initial_mass * (-age_gy / DISK_LIFETIME).exp();
+
+        if mass < 1e-5 * star_mass {
+            return None;
+        }
+
+        //Hartmann et al.: accretion scales with stellar mass squared and drops off with age
+        let age_myr = (age_gy * 1000.0).max(0.1);
+        let accretion_rate = 1e-8 * star_mass.powi(2) * age_myr.powf(-1.5);
+
+        Some(CircumstellarDisk {
+            inner_radius: au(0.07 * luminosity.sqrt()),
+            outer_radius: au(100.0 * star_mass.sqrt()),
+            frost_line: au(2.7 * luminosity.sqrt()),
+            mass,
+            accretion_rate,
+        })
+    }
+
+    ///Luminosity (solar units) released by gas falling onto a star of this mass and radius (solar units).
+    pub fn accretion_luminosity(&self, star_mass: f64, star_radius: f64) -> f64 {
+        let accretion_rate = sols(self.accretion_rate) / years(1.0);
+        G * sols(star_mass) * accretion_rate / (star_radius * SOL_RADIUS) / SOL_LUMINOSITY
+    }
+
+    ///Surface density relative to the inner edge, falling off as 1/r. Zero outside the disk.
+    pub fn relative_density(&self, r: f64) -> f64 {
+        if r < self.inner_radius || r > self.outer_radius {
+            return 0.0;
+        }
+        self.inner_radius / r
+    }
+}
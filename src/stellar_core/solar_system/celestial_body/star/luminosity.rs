@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-//to be used by lighting system & for calculating heat/radiation
+///Luminosity in solar units. Warms the planets and pushes on ships, see navigation::radiation.
 #[derive(Component)]
 pub struct Luminosity(pub f64);
 
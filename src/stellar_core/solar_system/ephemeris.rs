@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy::math::DVec2;
+
+use super::{Atmosphere, Lagrange, Luminosity, Orbit, Planet, Star};
+use crate::stellar_core::floating_origin::WorldPosition;
+use crate::stellar_utils::unit_conversion::*;
+
+///One massive body, and how to work out where it is at any time.
+#[derive(Clone)]
+pub struct EphemerisBody {
+    ///Mass in kg.
+    pub mass: f64,
+    ///Radius in meters.
+    pub radius: f64,
+    ///False for bodies there's no landing on, like stars.
+    pub solid: bool,
+    pub atmosphere: Option<Atmosphere>,
+    ///Power given off as light, in watts. Zero for anything that doesn't shine.
+    pub luminosity: f64,
+    ///Radius of the sphere of influence in meters. Stars rule everything no planet does, so theirs is infinite.
+    pub soi: f64,
+    ///The orbit around the parent, or None for bodies that stay put.
+    pub orbit: Option<Orbit>,
+    //where a body that doesn't orbit anything sits, in meters
+    anchor: DVec2,
+}
+
+///Every massive body in the system, so positions can be evaluated at any time rather than just now.
+///Cheap to clone, so a snapshot can be handed to a background task.
+///Lagrange points are kept apart from the bodies, since they have no mass and nothing to collide with.
+#[derive(Resource, Clone, Default)]
+pub struct Ephemeris {
+    bodies: HashMap<Entity, EphemerisBody>,
+    lagrange: HashMap<Entity, Lagrange>,
+}
+
+impl Ephemeris {
+    ///Rebuilds the ephemeris from the bodies' current orbits and masses.
+    pub fn update(
+        mut ephemeris: ResMut<Ephemeris>,
+        stars: Query<(Entity, &Star, &Luminosity, &WorldPosition), Without<Planet>>,
+        planets: Query<(Entity, &Planet), Without<Star>>,
+        lagrange: Query<(Entity, &Lagrange)>,
+    ) {
+        ephemeris.bodies.clear();
+        ephemeris.lagrange = lagrange.iter().map(|(entity, point)| (entity, *point)).collect();
+
+        for (entity, star, luminosity, position) in stars.iter() {
+            ephemeris.bodies.insert(entity, EphemerisBody {
+                mass: sols(star.mass), radius: star.radius * SOL_RADIUS, solid: false, atmosphere: None,
+                luminosity: **luminosity * SOL_LUMINOSITY, soi: f64::INFINITY, orbit: None, anchor: **position
+            });
+        }
+
+        //planets need their parent's mass for the size of their sphere of influence
+        let masses: HashMap<Entity, f64> = stars.iter()
+            .map(|(entity, star, _, _)| (entity, sols(star.mass)))
+            .chain(planets.iter().map(|(entity, planet)| (entity, earths(planet.mass))))
+            .collect();
+
+        for (entity, planet) in planets.iter() {
+            let mass = earths(planet.mass);
+            let soi = match masses.get(&planet.orbit.parent) {
+                Some(&parent_mass) => planet.orbit.sphere_of_influence(mass, parent_mass),
+                None => 0.0,
+            };
+
+            //planet radius is in km
+            ephemeris.bodies.insert(entity, EphemerisBody {
+                mass, radius: planet.radius * 1000.0, solid: true, atmosphere: planet.atmosphere(), luminosity: 0.0, soi, orbit: Some(planet.orbit.clone()), anchor: DVec2::ZERO
+            });
+        }
+    }
+
+    ///Adds a body by hand, for tests that have no world to build the ephemeris from.
+    #[cfg(test)]
+    pub(crate) fn insert(&mut self, entity: Entity, mass: f64, radius: f64, orbit: Option<Orbit>) {
+        self.bodies.insert(entity, EphemerisBody {
+            mass, radius, solid: orbit.is_some(), atmosphere: None, luminosity: 0.0,
+            soi: f64::INFINITY, orbit, anchor: DVec2::ZERO
+        });
+    }
+
+    pub fn body(&self, entity: Entity) -> Option<&EphemerisBody> {
+        self.bodies.get(&entity)
+    }
+
+    ///Mass of a body in kg, or 0 if it isn't in the ephemeris.
+    pub fn mass(&self, entity: Entity) -> f64 {
+        self.bodies.get(&entity).map_or(0.0, |body| body.mass)
+    }
+
+    ///The body this one orbits, if it orbits anything.
+    pub fn parent(&self, entity: Entity) -> Option<Entity> {
+        let parent = self.bodies.get(&entity)?.orbit.as_ref()?.parent;
+        self.bodies.contains_key(&parent).then_some(parent)
+    }
+
+    ///True if descendant orbits ancestor, directly or through other bodies.
+    pub fn is_ancestor(&self, ancestor: Entity, descendant: Entity) -> bool {
+        let mut current = descendant;
+        while let Some(parent) = self.parent(current) {
+            if parent == ancestor {
+                return true;
+            }
+            current = parent;
+        }
+
+        false
+    }
+
+    ///A name for a body from where it sits, since they aren't given any: stars counted by mass, biggest first,
+    ///and everything else counted out from its parent, like "PLANET 3" or "MOON 1 OF PLANET 3".
+    pub fn name(&self, entity: Entity) -> String {
+        let Some(body) = self.bodies.get(&entity) else { return "UNKNOWN".to_string() };
+        let Some(orbit) = &body.orbit else {
+            let heavier = self.bodies.values().filter(|other| other.orbit.is_none() && other.mass > body.mass).count();
+            return format!("STAR {}", heavier + 1);
+        };
+
+        let inside = self.bodies.values()
+            .filter_map(|other| other.orbit.as_ref())
+            .filter(|other| other.parent == orbit.parent && other.semi_major_axis() < orbit.semi_major_axis())
+            .count();
+        match self.bodies.get(&orbit.parent).is_some_and(|parent| parent.orbit.is_some()) {
+            true => format!("MOON {} OF {}", inside + 1, self.name(orbit.parent)),
+            false => format!("PLANET {}", inside + 1),
+        }
+    }
+
+    ///Every body and its entity.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &EphemerisBody)> {
+        self.bodies.iter().map(|(entity, body)| (*entity, body))
+    }
+
+    ///Every Lagrange point and its entity.
+    pub fn lagrange_points(&self) -> impl Iterator<Item = (Entity, &Lagrange)> {
+        self.lagrange.iter().map(|(entity, point)| (*entity, point))
+    }
+
+    ///Position (m) and velocity (m/s) of a body or Lagrange point at time t, following its parents up the hierarchy.
+    pub fn state_at(&self, entity: Entity, t: f64) -> Option<(DVec2, DVec2)> {
+        if let Some(lagrange) = self.lagrange.get(&entity) {
+            return self.lagrange_state_at(lagrange, t);
+        }
+
+        let body = self.bodies.get(&entity)?;
+        let Some(orbit) = &body.orbit else { return Some((body.anchor, DVec2::ZERO)) };
+
+        let (parent_position, parent_velocity) = self.state_at(orbit.parent, t).unwrap_or_default();
+        let (position, velocity) = orbit.state_at_time(t, body.mass, self.mass(orbit.parent));
+
+        Some((parent_position + position.truncate(), parent_velocity + velocity.truncate()))
+    }
+
+    //the point turns with the secondary and stretches with their separation
+    fn lagrange_state_at(&self, lagrange: &Lagrange, t: f64) -> Option<(DVec2, DVec2)> {
+        let (primary_position, primary_velocity) = self.state_at(lagrange.primary, t)?;
+        let (secondary_position, secondary_velocity) = self.state_at(lagrange.secondary, t)?;
+        let (position, velocity) = (secondary_position - primary_position, secondary_velocity - primary_velocity);
+
+        let offset = lagrange.point.offset(position, velocity, self.mass(lagrange.primary), self.mass(lagrange.secondary));
+        let separation = position.length_squared();
+        let spin = position.perp_dot(velocity) / separation;
+        let stretch = position.dot(velocity) / separation;
+
+        Some((primary_position + offset, primary_velocity + offset * stretch + offset.perp() * spin))
+    }
+
+    ///Position (m) of a body at time t.
+    pub fn position_at(&self, entity: Entity, t: f64) -> Option<DVec2> {
+        self.state_at(entity, t).map(|(position, _)| position)
+    }
+
+    ///The body whose sphere of influence this position (m) is in at time t.
+    ///Spheres nest, so that's the smallest one containing it, or the heaviest star outside all of them.
+    pub fn dominant_body(&self, position: DVec2, t: f64) -> Option<Entity> {
+        self.bodies.iter()
+            .filter(|(entity, body)| match body.soi.is_finite() {
+                true => self.position_at(**entity, t)
+                    .is_some_and(|centre| centre.distance(position) < body.soi),
+                false => true,
+            })
+            .min_by(|(_, a), (_, b)| a.soi.total_cmp(&b.soi).then(b.mass.total_cmp(&a.mass)))
+            .map(|(entity, _)| *entity)
+    }
+}
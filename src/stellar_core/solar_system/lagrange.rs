@@ -0,0 +1,238 @@
+use std::f64::consts::PI;
+
+use bevy::prelude::*;
+use bevy::math::DVec2;
+
+use super::{Mass, Planet};
+use crate::stellar_core::floating_origin::{FloatingOrigin, WorldPosition};
+use crate::stellar_core::navigation::PredictionConfig;
+
+///Newton's method stops once the collinear points move less than this, in separations of the pair.
+const TOLERANCE: f64 = 1e-12;
+const MAX_ITERATIONS: u32 = 50;
+///How close the cursor has to be to a marker to hover over it, in pixels.
+const HOVER_RADIUS: f32 = 10.0;
+///Half the width of a drawn marker, in pixels.
+const MARKER_SIZE: f32 = 4.0;
+
+///The five points where a small body can sit still relative to a pair of bodies orbiting each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LagrangePoint {
+    ///Between the two.
+    L1,
+    ///Beyond the secondary.
+    L2,
+    ///Opposite the secondary, beyond the primary.
+    L3,
+    ///Sixty degrees ahead of the secondary.
+    L4,
+    ///Sixty degrees behind the secondary.
+    L5,
+}
+
+impl LagrangePoint {
+    pub const ALL: [LagrangePoint; 5] = [
+        LagrangePoint::L1, LagrangePoint::L2, LagrangePoint::L3, LagrangePoint::L4, LagrangePoint::L5
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LagrangePoint::L1 => "L1",
+            LagrangePoint::L2 => "L2",
+            LagrangePoint::L3 => "L3",
+            LagrangePoint::L4 => "L4",
+            LagrangePoint::L5 => "L5",
+        }
+    }
+
+    ///Where the point is relative to the primary (m), given the secondary's position (m) and velocity (m/s)
+    ///relative to the primary and both masses (kg). The points turn with the secondary, so eccentric
+    ///orbits get the points for the separation at that moment.
+    pub fn offset(&self, position: DVec2, velocity: DVec2, primary_mass: f64, secondary_mass: f64) -> DVec2 {
+        let mu = secondary_mass / (primary_mass + secondary_mass);
+        //which way round the secondary goes, so L4 leads whichever way that is
+        let ahead = match position.perp_dot(velocity) < 0.0 {
+            true => -1.0,
+            false => 1.0,
+        };
+
+        match self {
+            LagrangePoint::L4 => DVec2::from_angle(ahead * PI / 3.0).rotate(position),
+            LagrangePoint::L5 => DVec2::from_angle(-ahead * PI / 3.0).rotate(position),
+            collinear => position * (collinear.solve(mu) + mu),
+        }
+    }
+
+    //position of a collinear point along the line through both bodies, in the rotating frame where the
+    //barycentre is at 0, the primary at -mu and the secondary at 1 - mu. newton's method from the hill sphere
+    fn solve(&self, mu: f64) -> f64 {
+        let hill = (mu / 3.0).cbrt();
+        let mut x = match self {
+            LagrangePoint::L1 => 1.0 - mu - hill,
+            LagrangePoint::L2 => 1.0 - mu + hill,
+            _ => -1.0 - 5.0 * mu / 12.0,
+        };
+
+        //with no mass to speak of, L1 and L2 sit on the secondary itself
+        if mu <= 0.0 {
+            return x;
+        }
+
+        for _ in 0..MAX_ITERATIONS {
+            let (r1, r2) = (x + mu, x - 1.0 + mu);
+            let force = x - (1.0 - mu) * r1 / r1.abs().powi(3) - mu * r2 / r2.abs().powi(3);
+            let slope = 1.0 + 2.0 * (1.0 - mu) / r1.abs().powi(3) + 2.0 * mu / r2.abs().powi(3);
+
+            let step = force / slope;
+            x -= step;
+            if step.abs() < TOLERANCE {
+                break;
+            }
+        }
+
+        x
+    }
+}
+
+///A Lagrange point of a body and the one it orbits. Its WorldPosition follows the pair around.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Lagrange {
+    pub primary: Entity,
+    pub secondary: Entity,
+    pub point: LagrangePoint,
+}
+
+///The Lagrange point under the cursor, if there is one.
+#[derive(Resource, Debug, Default)]
+pub struct HoveredLagrange(pub Option<Entity>);
+
+impl Lagrange {
+    ///Every planet and moon gets the five points of it and its parent.
+    pub fn spawn(mut commands: Commands, planets: Query<(Entity, &Planet)>, bodies: Query<(), With<Mass>>) {
+        for (entity, planet) in planets.iter() {
+            if !bodies.contains(planet.orbit.parent) {
+                continue;
+            }
+
+            for point in LagrangePoint::ALL {
+                commands.spawn((
+                    Lagrange { primary: planet.orbit.parent, secondary: entity, point },
+                    WorldPosition(DVec2::ZERO),
+                ));
+            }
+        }
+    }
+
+    ///Finds the marker closest to the cursor, within reach of it.
+    pub fn hover(
+        origin: Res<FloatingOrigin>,
+        mut hovered: ResMut<HoveredLagrange>,
+        points: Query<(Entity, &WorldPosition), With<Lagrange>>,
+        camera_query: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+        q_windows: Query<&Window, With<bevy::window::PrimaryWindow>>
+    ) {
+        hovered.0 = None;
+
+        let Ok((camera, camera_transform)) = camera_query.get_single() else { return };
+        let Ok(window) = q_windows.get_single() else { return };
+        let Some(cursor_pos) = window.cursor_position() else { return };
+        let Ok(cursor_pos) = camera.viewport_to_world_2d(camera_transform, cursor_pos) else { return };
+
+        //the reach is in pixels, so it has to grow as the camera zooms out
+        let reach = HOVER_RADIUS * camera_transform.compute_transform().scale.x;
+
+        hovered.0 = points.iter()
+            .map(|(entity, position)| (entity, origin.to_render(**position).distance(cursor_pos)))
+            .filter(|(_, distance)| *distance < reach)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entity, _)| entity);
+    }
+
+    ///Draws each point as a small cross, brighter when it's hovered over or targeted.
+    pub fn draw(
+        mut gizmos: Gizmos,
+        origin: Res<FloatingOrigin>,
+        hovered: Res<HoveredLagrange>,
+        config: Res<PredictionConfig>,
+        points: Query<(Entity, &WorldPosition), With<Lagrange>>,
+        camera_query: Query<&GlobalTransform, With<Camera2d>>
+    ) {
+        let size = MARKER_SIZE * camera_query.get_single().map_or(1.0, |transform| transform.compute_transform().scale.x);
+
+        for (entity, position) in points.iter() {
+            let centre = origin.to_render(**position);
+            let color = match hovered.0 == Some(entity) || config.target == Some(entity) {
+                true => Color::srgb(0.6, 1.0, 0.6),
+                false => Color::srgba(0.6, 1.0, 0.6, 0.3),
+            };
+
+            gizmos.line_2d(centre - Vec2::splat(size), centre + Vec2::splat(size), color);
+            gizmos.line_2d(centre + Vec2::new(-size, size), centre + Vec2::new(size, -size), color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stellar_utils::unit_conversion::{au, earths, sols};
+
+    //the sun and earth on a circle, the earth going round anticlockwise
+    fn earth_sun(point: LagrangePoint) -> DVec2 {
+        point.offset(DVec2::new(au(1.0), 0.0), DVec2::new(0.0, 29_780.0), sols(1.0), earths(1.0))
+    }
+
+    #[test]
+    fn earth_sun_l1_and_l2() {
+        //about 1.5 million km either side of the earth
+        let l1 = au(1.0) - earth_sun(LagrangePoint::L1).x;
+        let l2 = earth_sun(LagrangePoint::L2).x - au(1.0);
+        assert!((l1 / 1.4915e9 - 1.0).abs() < 1e-3, "L1 {l1}");
+        assert!((l2 / 1.5015e9 - 1.0).abs() < 1e-3, "L2 {l2}");
+        //L2 is further out, where the sun pulls less
+        assert!(l2 > l1);
+
+        assert!(earth_sun(LagrangePoint::L1).y.abs() < 1e-3);
+        assert!(earth_sun(LagrangePoint::L2).y.abs() < 1e-3);
+    }
+
+    #[test]
+    fn collinear_points_balance() {
+        for mu in [1e-9, 3.0e-6, 0.0123, 0.3] {
+            for point in [LagrangePoint::L1, LagrangePoint::L2, LagrangePoint::L3] {
+                let x = point.solve(mu);
+                let (r1, r2) = (x + mu, x - 1.0 + mu);
+                let force = x - (1.0 - mu) * r1 / r1.abs().powi(3) - mu * r2 / r2.abs().powi(3);
+                assert!(force.abs() < 1e-9, "{point:?} at mu = {mu}: {force}");
+            }
+
+            //in order along the line: L3 beyond the primary, L1 between the two, L2 beyond the secondary
+            let (l1, l2, l3) = (LagrangePoint::L1.solve(mu), LagrangePoint::L2.solve(mu), LagrangePoint::L3.solve(mu));
+            assert!(l3 < -mu && -mu < l1 && l1 < 1.0 - mu && 1.0 - mu < l2, "mu = {mu}: {l3} {l1} {l2}");
+        }
+    }
+
+    #[test]
+    fn massless_secondary_holds_its_points() {
+        //nothing to balance against, so the collinear points fall back to where they'd start
+        assert_eq!(LagrangePoint::L1.solve(0.0), 1.0);
+        assert_eq!(LagrangePoint::L2.solve(0.0), 1.0);
+        assert_eq!(LagrangePoint::L3.solve(0.0), -1.0);
+    }
+
+    #[test]
+    fn triangular_points_lead_and_trail() {
+        let position = DVec2::new(au(1.0), 0.0);
+        let l4 = earth_sun(LagrangePoint::L4);
+        let l5 = earth_sun(LagrangePoint::L5);
+
+        //equilateral with the sun and earth, L4 ahead in the direction of travel
+        assert!((l4.length() / au(1.0) - 1.0).abs() < 1e-12);
+        assert!((l4.distance(position) / au(1.0) - 1.0).abs() < 1e-12);
+        assert!(l4.y > 0.0 && l5.y < 0.0);
+
+        //going round the other way swaps them
+        let clockwise = LagrangePoint::L4.offset(position, DVec2::new(0.0, -29_780.0), sols(1.0), earths(1.0));
+        assert!((clockwise - l5).length() < 1e-3);
+    }
+}
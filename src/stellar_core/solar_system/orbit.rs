@@ -1,21 +1,72 @@
-use std::f64::consts::PI;
+use std::f64::consts::{PI, TAU};
 use bevy::prelude::*;
-pub const G: f64 = 6.6743015e-11;
+use bevy::math::{DVec3, DQuat};
+pub use crate::stellar_utils::unit_conversion::G;
 
 //N.B.: This module uses SI units.
 //Make sure that distances should be in meters, masses in kilograms, and periods in seconds.
+//Angles are in radians. The reference plane is the XY plane the game is drawn in, with +X as
+//the reference direction, so an orbit with no inclination stays flat on screen.
+
+///Below this, orbits are treated as circular or equatorial when converting from state vectors.
+const ANGLE_EPSILON: f64 = 1e-11;
+///Orbits whose eccentricity is this close to 1 are treated as parabolic.
+const PARABOLIC_EPSILON: f64 = 1e-9;
+///Default tolerance (rad) for the Kepler solvers.
+pub const KEPLER_TOLERANCE: f64 = 1e-12;
+const MAX_KEPLER_ITERATIONS: u32 = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Conic {
+    Ellipse,
+    Parabola,
+    Hyperbola,
+}
+
+///Outcome of solving Kepler's equation.
+#[derive(Clone, Copy, Debug)]
+pub struct KeplerSolution {
+    ///Eccentric, hyperbolic or parabolic anomaly, depending on the orbit.
+    pub anomaly: f64,
+    pub iterations: u32,
+    ///How far the solution is from satisfying the equation, in radians of mean anomaly.
+    pub residual: f64,
+    ///False if the solver ran out of iterations before reaching the tolerance.
+    pub converged: bool,
+}
 
 #[derive(Clone)]
 pub struct Orbit {
-    parent: Entity,
-    pub apoapsis: f64,
+    ///Entity this orbit is around. Entity::PLACEHOLDER orbits the world origin.
+    pub parent: Entity,
+    ///Closest approach to the parent (q), in meters.
     pub periapsis: f64,
-    pub offset: f64,
+    ///Eccentricity (e). 0 is a circle.
+    pub eccentricity: f64,
+    ///Inclination (i) of the orbital plane to the reference plane.
+    pub inclination: f64,
+    ///Longitude of the ascending node (Ω), measured from +X.
+    pub ascending_node: f64,
+    ///Argument of periapsis (ω), measured from the ascending node.
+    pub argument_of_periapsis: f64,
+    ///Mean anomaly at epoch (M0).
+    pub mean_anomaly: f64,
+    ///Time in seconds at which the body was at mean_anomaly.
+    pub epoch: f64,
 }
 
 impl Default for Orbit {
     fn default() -> Self {
-        Orbit { parent: Entity::PLACEHOLDER, apoapsis: 0.0, periapsis: 0.0, offset: 0.0 }
+        Orbit {
+            parent: Entity::PLACEHOLDER,
+            periapsis: 0.0,
+            eccentricity: 0.0,
+            inclination: 0.0,
+            ascending_node: 0.0,
+            argument_of_periapsis: 0.0,
+            mean_anomaly: 0.0,
+            epoch: 0.0,
+        }
     }
 }
 
@@ -24,9 +75,9 @@ impl std::fmt::Debug for Orbit {
         f.write_str(
             format!(
                 "A{:.0} P{:.0} E{:.2}",
-                self.apoapsis,
+                self.apoapsis(),
                 self.periapsis,
-                self.eccentricity()
+                self.eccentricity
             )
             .as_str()
         )
@@ -35,58 +86,514 @@ impl std::fmt::Debug for Orbit {
 
 impl Orbit {
 
+    ///Flat orbit with its periapsis along +X, starting offset radians of mean anomaly along at t = 0.
     pub fn new(apoapsis: f64, periapsis: f64, offset: f64) -> Orbit {
-        Orbit { parent: Entity::PLACEHOLDER, apoapsis, periapsis, offset }
+        Orbit {
+            periapsis,
+            eccentricity: match apoapsis + periapsis > 0.0 {
+                true => (apoapsis - periapsis) / (apoapsis + periapsis),
+                false => 0.0,
+            },
+            mean_anomaly: offset,
+            ..default()
+        }
+    }
+
+    ///Orbit from the classical elements: semi-major axis (m), eccentricity, inclination,
+    ///longitude of the ascending node, argument of periapsis, and mean anomaly at epoch (s).
+    pub fn from_elements(
+        semi_major_axis: f64, eccentricity: f64, inclination: f64, ascending_node: f64,
+        argument_of_periapsis: f64, mean_anomaly: f64, epoch: f64
+    ) -> Orbit {
+        Orbit {
+            parent: Entity::PLACEHOLDER,
+            periapsis: semi_major_axis * (1.0 - eccentricity),
+            eccentricity,
+            inclination,
+            ascending_node,
+            argument_of_periapsis,
+            mean_anomaly,
+            epoch,
+        }
     }
+
+    ///Orbit that passes through this position (m) with this velocity (m/s) relative to the parent at time epoch (s).
+    pub fn from_state_vectors(position: DVec3, velocity: DVec3, mass: f64, parent_mass: f64, epoch: f64) -> Orbit {
+        let mu = G * (mass + parent_mass);
+        let r = position.length();
+
+        //specific angular momentum, and the node vector pointing at the ascending node
+        let h = position.cross(velocity);
+        let node = DVec3::Z.cross(h);
+
+        let e_vec = ((velocity.length_squared() - mu / r) * position - position.dot(velocity) * velocity) / mu;
+        let eccentricity = e_vec.length();
+        let periapsis = h.length_squared() / mu / (1.0 + eccentricity);
+
+        let inclination = (h.z / h.length()).clamp(-1.0, 1.0).acos();
+        let equatorial = node.length() < ANGLE_EPSILON * h.length();
+        let circular = eccentricity < ANGLE_EPSILON;
+        //in the reference plane, retrograde orbits measure their angles the other way around
+        let handedness = h.z.signum();
+
+        let ascending_node = match equatorial {
+            true => 0.0,
+            false => node.y.atan2(node.x).rem_euclid(TAU),
+        };
+
+        //angle from the ascending node (or +X when there is none) to a vector in the orbital plane
+        let angle_from_node = |v: DVec3| -> f64 {
+            match equatorial {
+                true => (handedness * v.y.atan2(v.x)).rem_euclid(TAU),
+                false => {
+                    let angle = (node.dot(v) / (node.length() * v.length())).clamp(-1.0, 1.0).acos();
+                    match v.z < 0.0 { true => TAU - angle, false => angle }
+                }
+            }
+        };
+
+        //circular orbits have no periapsis, so put it on the node and measure the anomaly from there
+        let (argument_of_periapsis, true_anomaly) = match circular {
+            true => (0.0, angle_from_node(position)),
+            false => {
+                let argument_of_periapsis = angle_from_node(e_vec);
+                (argument_of_periapsis, (angle_from_node(position) - argument_of_periapsis).rem_euclid(TAU))
+            }
+        };
+
+        let mut orbit = Orbit {
+            parent: Entity::PLACEHOLDER,
+            periapsis,
+            eccentricity,
+            inclination,
+            ascending_node,
+            argument_of_periapsis,
+            mean_anomaly: 0.0,
+            epoch,
+        };
+        //escape trajectories count their anomaly from -π on the way in to π on the way out
+        let true_anomaly = match orbit.is_bound() {
+            true => true_anomaly,
+            false => (true_anomaly + PI).rem_euclid(TAU) - PI,
+        };
+        orbit.mean_anomaly = orbit.mean_from_eccentric(orbit.eccentric_from_true(true_anomaly));
+
+        orbit
+    }
+
+    ///Which kind of conic section this orbit traces.
+    pub fn conic(&self) -> Conic {
+        match self.eccentricity {
+            e if (e - 1.0).abs() < PARABOLIC_EPSILON => Conic::Parabola,
+            e if e < 1.0 => Conic::Ellipse,
+            _ => Conic::Hyperbola,
+        }
+    }
+    ///True if the body will come back around, rather than escaping its parent.
+    pub fn is_bound(&self) -> bool {
+        self.conic() == Conic::Ellipse
+    }
+
+    ///Negative for hyperbolic orbits and infinite for parabolic ones.
     pub fn semi_major_axis(self: &Self) -> f64 {
-        (self.apoapsis + self.periapsis) / 2.0
+        match self.conic() {
+            Conic::Parabola => f64::INFINITY,
+            _ => self.periapsis / (1.0 - self.eccentricity),
+        }
+    }
+    ///Farthest distance from the parent, in meters. Infinite for escape trajectories.
+    pub fn apoapsis(self: &Self) -> f64 {
+        match self.conic() {
+            Conic::Ellipse => self.semi_major_axis() * (1.0 + self.eccentricity),
+            _ => f64::INFINITY,
+        }
     }
     pub fn semi_minor_axis(self: &Self) -> f64 {
-        (self.apoapsis * self.periapsis).sqrt()
+        match self.conic() {
+            Conic::Ellipse => self.semi_major_axis() * (1.0 - self.eccentricity.powi(2)).sqrt(),
+            Conic::Parabola => f64::INFINITY,
+            Conic::Hyperbola => -self.semi_major_axis() * (self.eccentricity.powi(2) - 1.0).sqrt(),
+        }
     }
     pub fn semi_latus(self: &Self) -> f64 {
-        ((self.apoapsis.powi(-1) + self.periapsis.powi(-1)) / 2.0).powi(-1)
-    }
-    pub fn eccentricity(self: &Self) -> f64 {
-        (self.apoapsis - self.periapsis) / (self.apoapsis + self.periapsis)
+        self.periapsis * (1.0 + self.eccentricity)
     }
+    ///Infinite for escape trajectories.
     pub fn period(self: &Self, mass: f64, parent_mass: f64) -> f64 {
-        (self.semi_major_axis().powi(3) / (G * (mass + parent_mass))).sqrt() * 2.0 * PI
+        match self.conic() {
+            Conic::Ellipse => (self.semi_major_axis().powi(3) / (G * (mass + parent_mass))).sqrt() * 2.0 * PI,
+            _ => f64::INFINITY,
+        }
     }
 
-    ///Average angular velocity (rad/s).
+    ///Average angular velocity (rad/s). For escape trajectories this is the rate the
+    ///hyperbolic or parabolic mean anomaly grows at.
     pub fn mean_motion(&self, mass: f64, parent_mass: f64) -> f64 {
-        (G * (mass + parent_mass) / self.semi_major_axis().powi(3)).sqrt()
+        let mu = G * (mass + parent_mass);
+        match self.conic() {
+            Conic::Ellipse => (mu / self.semi_major_axis().powi(3)).sqrt(),
+            Conic::Parabola => 2.0 * (mu / self.semi_latus().powi(3)).sqrt(),
+            Conic::Hyperbola => (mu / (-self.semi_major_axis()).powi(3)).sqrt(),
+        }
+    }
+
+    ///Mean anomaly at time t (seconds).
+    pub fn mean_anomaly_at_time(&self, t: f64, mass: f64, parent_mass: f64) -> f64 {
+        self.mean_anomaly + self.mean_motion(mass, parent_mass) * (t - self.epoch)
+    }
+
+    ///True anomaly at time t (seconds): the angle between periapsis and the body, seen from the parent.
+    pub fn true_anomaly_at_time(&self, t: f64, mass: f64, parent_mass: f64) -> f64 {
+        let mean_anomaly = self.mean_anomaly_at_time(t, mass, parent_mass);
+        self.true_from_eccentric(self.eccentric_from_mean(mean_anomaly))
+    }
+
+    ///Eccentric anomaly for this mean anomaly. See solve_kepler.
+    pub fn eccentric_from_mean(&self, mean_anomaly: f64) -> f64 {
+        self.solve_kepler(mean_anomaly, KEPLER_TOLERANCE).anomaly
+    }
+
+    ///Solves Kepler's equation for this mean anomaly to within tolerance (rad).
+    ///Ellipses give the eccentric anomaly E, hyperbolas the hyperbolic anomaly H,
+    ///and parabolas tan(ν/2) from Barker's equation, which has a closed form.
+    pub fn solve_kepler(&self, mean_anomaly: f64, tolerance: f64) -> KeplerSolution {
+        let e = self.eccentricity;
+
+        let (mut anomaly, f, f_prime): (f64, fn(f64, f64, f64) -> f64, fn(f64, f64) -> f64) = match self.conic() {
+            Conic::Parabola => {
+                //D^3 + 3D - 3M = 0, solved with Cardano's formula
+                let w = 1.5 * mean_anomaly;
+                let root = (w * w + 1.0).sqrt();
+                let anomaly = (w + root).cbrt() + (w - root).cbrt();
+
+                return KeplerSolution {
+                    anomaly,
+                    iterations: 0,
+                    residual: (anomaly + anomaly.powi(3) / 3.0 - mean_anomaly).abs(),
+                    converged: true,
+                };
+            },
+            Conic::Ellipse => {
+                //starting from pi converges for every elliptical orbit, even very eccentric ones
+                let mean_anomaly = mean_anomaly.rem_euclid(TAU);
+                (
+                    match e < 0.8 { true => mean_anomaly, false => PI },
+                    |x, e, m| x - e * x.sin() - m.rem_euclid(TAU),
+                    |x, e| 1.0 - e * x.cos(),
+                )
+            },
+            Conic::Hyperbola => (
+                mean_anomaly.signum() * (2.0 * mean_anomaly.abs() / e + 1.8).ln(),
+                |x, e, m| e * x.sinh() - x - m,
+                |x, e| e * x.cosh() - 1.0,
+            ),
+        };
+
+        //Newton-Raphson, usually done in under 5 iterations
+        for iterations in 1..=MAX_KEPLER_ITERATIONS {
+            let step = f(anomaly, e, mean_anomaly) / f_prime(anomaly, e);
+            anomaly -= step;
+
+            if step.abs() < tolerance {
+                return KeplerSolution {
+                    anomaly,
+                    iterations,
+                    residual: f(anomaly, e, mean_anomaly).abs(),
+                    converged: true,
+                };
+            }
+        }
+
+        KeplerSolution {
+            anomaly,
+            iterations: MAX_KEPLER_ITERATIONS,
+            residual: f(anomaly, e, mean_anomaly).abs(),
+            converged: false,
+        }
     }
 
-    //Solve Kepler's Equation with Newton-Raphson method
-    fn eccentric_anomaly(&self, mean_anomaly: f64, e: f64) -> f64 {
-        let mut e_anom = mean_anomaly;  //initial guess
-        for _ in 0..10 {    //usually converges in <5 iterations
-            let f = e_anom - e * e_anom.sin() - mean_anomaly;
-            let f_prime = 1.0 - e * e_anom.cos();
-            e_anom -= f / f_prime;
+    ///Mean anomaly for this eccentric, hyperbolic or parabolic anomaly.
+    pub fn mean_from_eccentric(&self, eccentric_anomaly: f64) -> f64 {
+        let e = self.eccentricity;
+        match self.conic() {
+            Conic::Ellipse => eccentric_anomaly - e * eccentric_anomaly.sin(),
+            Conic::Parabola => eccentric_anomaly + eccentric_anomaly.powi(3) / 3.0,
+            Conic::Hyperbola => e * eccentric_anomaly.sinh() - eccentric_anomaly,
         }
-        e_anom
+    }
+
+    ///True anomaly for this eccentric, hyperbolic or parabolic anomaly.
+    ///Escape trajectories run from -π to π, with negative values on the way in.
+    pub fn true_from_eccentric(&self, eccentric_anomaly: f64) -> f64 {
+        let e = self.eccentricity;
+        match self.conic() {
+            Conic::Ellipse => {
+                let half = ((1.0 + e) / (1.0 - e)).sqrt() * (eccentric_anomaly / 2.0).tan();
+                (2.0 * half.atan()).rem_euclid(TAU)
+            },
+            Conic::Parabola => 2.0 * eccentric_anomaly.atan(),
+            Conic::Hyperbola => {
+                let half = ((e + 1.0) / (e - 1.0)).sqrt() * (eccentric_anomaly / 2.0).tanh();
+                2.0 * half.atan()
+            },
+        }
+    }
+
+    ///Eccentric, hyperbolic or parabolic anomaly for this true anomaly.
+    pub fn eccentric_from_true(&self, true_anomaly: f64) -> f64 {
+        let e = self.eccentricity;
+        match self.conic() {
+            Conic::Ellipse => {
+                let half = ((1.0 - e) / (1.0 + e)).sqrt() * (true_anomaly / 2.0).tan();
+                (2.0 * half.atan()).rem_euclid(TAU)
+            },
+            Conic::Parabola => (true_anomaly / 2.0).tan(),
+            Conic::Hyperbola => {
+                let half = ((e - 1.0) / (e + 1.0)).sqrt() * (true_anomaly / 2.0).tan();
+                2.0 * half.atanh()
+            },
+        }
+    }
+
+    ///Largest true anomaly an escape trajectory reaches, along its outgoing asymptote.
+    ///π for parabolas, and None for closed orbits.
+    pub fn true_anomaly_limit(&self) -> Option<f64> {
+        match self.conic() {
+            Conic::Ellipse => None,
+            Conic::Parabola => Some(PI),
+            Conic::Hyperbola => Some((-1.0 / self.eccentricity).acos()),
+        }
+    }
+
+    ///Speed (m/s) needed to escape a parent from this distance (m).
+    pub fn escape_velocity(distance: f64, mass: f64, parent_mass: f64) -> f64 {
+        (2.0 * G * (mass + parent_mass) / distance).sqrt()
+    }
+
+    ///Speed (m/s) left over once the body has escaped: v∞ for hyperbolas, 0 for parabolas.
+    ///None for closed orbits, which never escape.
+    pub fn excess_velocity(&self, mass: f64, parent_mass: f64) -> Option<f64> {
+        match self.conic() {
+            Conic::Ellipse => None,
+            Conic::Parabola => Some(0.0),
+            Conic::Hyperbola => Some((-G * (mass + parent_mass) / self.semi_major_axis()).sqrt()),
+        }
+    }
+
+    ///Laplace sphere of influence (m) of a body of this mass (kg) on this orbit.
+    pub fn sphere_of_influence(&self, mass: f64, parent_mass: f64) -> f64 {
+        self.semi_major_axis().abs() * (mass / parent_mass).powf(0.4)
+    }
+
+    ///First time (s) at or after t when the body leaves a sphere of influence of this radius (m),
+    ///or None if it never reaches that far or is already on its way out beyond it.
+    pub fn soi_exit_time(&self, t: f64, soi_radius: f64, mass: f64, parent_mass: f64) -> Option<f64> {
+        if soi_radius <= self.periapsis || soi_radius >= self.apoapsis() {
+            return None;
+        }
+
+        //outbound crossing of the sphere: r = p / (1 + e cos ν) solved for ν
+        let cos_exit = ((self.semi_latus() / soi_radius - 1.0) / self.eccentricity).clamp(-1.0, 1.0);
+        let exit_anomaly = self.mean_from_eccentric(self.eccentric_from_true(cos_exit.acos()));
+
+        let n = self.mean_motion(mass, parent_mass);
+        let now = self.mean_anomaly_at_time(t, mass, parent_mass);
+
+        match self.conic() {
+            Conic::Ellipse => Some(t + (exit_anomaly - now).rem_euclid(TAU) / n),
+            _ if now <= exit_anomaly => Some(t + (exit_anomaly - now) / n),
+            _ => None,
+        }
+    }
+
+    ///Distance from the parent (m) at this true anomaly.
+    pub fn radius_at(&self, true_anomaly: f64) -> f64 {
+        self.semi_latus() / (1.0 + self.eccentricity * true_anomaly.cos())
+    }
+
+    ///Position (m) relative to the parent at this true anomaly.
+    pub fn position_at_true_anomaly(&self, true_anomaly: f64) -> DVec3 {
+        let (sin, cos) = true_anomaly.sin_cos();
+        let r = self.radius_at(true_anomaly);
+
+        self.to_reference(DVec3::new(r * cos, r * sin, 0.0))
+    }
+
+    ///Seconds from time t until the next periapsis, or None if an escape trajectory has already passed it.
+    pub fn time_to_periapsis(&self, t: f64, mass: f64, parent_mass: f64) -> Option<f64> {
+        let n = self.mean_motion(mass, parent_mass);
+        let now = self.mean_anomaly_at_time(t, mass, parent_mass);
+
+        match self.conic() {
+            Conic::Ellipse => Some((-now).rem_euclid(TAU) / n),
+            _ if now <= 0.0 => Some(-now / n),
+            _ => None,
+        }
+    }
+
+    ///Seconds from time t until the next apoapsis. None for escape trajectories, which have none.
+    pub fn time_to_apoapsis(&self, t: f64, mass: f64, parent_mass: f64) -> Option<f64> {
+        let n = self.mean_motion(mass, parent_mass);
+        let now = self.mean_anomaly_at_time(t, mass, parent_mass);
+
+        match self.conic() {
+            Conic::Ellipse => Some((PI - now).rem_euclid(TAU) / n),
+            _ => None,
+        }
+    }
+
+    ///True anomaly at which an orbit in the reference plane points along this angle (rad) from +X.
+    pub fn true_anomaly_towards(&self, angle: f64) -> f64 {
+        //an inclination of π turns the orbit round the other way
+        let direction = self.inclination.cos().signum();
+        (direction * (angle - self.ascending_node) - self.argument_of_periapsis).rem_euclid(TAU)
+    }
+
+    ///Seconds from time t until the body reaches this true anomaly, or None if an escape trajectory has already passed it.
+    pub fn time_to_true_anomaly(&self, t: f64, true_anomaly: f64, mass: f64, parent_mass: f64) -> Option<f64> {
+        let n = self.mean_motion(mass, parent_mass);
+        let now = self.mean_anomaly_at_time(t, mass, parent_mass);
+        let then = self.mean_from_eccentric(self.eccentric_from_true(true_anomaly));
+
+        match self.conic() {
+            Conic::Ellipse => Some((then - now).rem_euclid(TAU) / n),
+            _ if now <= then => Some((then - now) / n),
+            _ => None,
+        }
+    }
+
+    ///Rotates a vector from the orbital plane (periapsis along +X) into the reference frame.
+    fn to_reference(&self, perifocal: DVec3) -> DVec3 {
+        let rotation = DQuat::from_rotation_z(self.ascending_node)
+            * DQuat::from_rotation_x(self.inclination)
+            * DQuat::from_rotation_z(self.argument_of_periapsis);
+
+        rotation * perifocal
+    }
+
+    ///Position (m) and velocity (m/s) relative to the parent at time t (seconds).
+    pub fn state_at_time(&self, t: f64, mass: f64, parent_mass: f64) -> (DVec3, DVec3) {
+        let mu = G * (mass + parent_mass);
+        let true_anomaly = self.true_anomaly_at_time(t, mass, parent_mass);
+        let (sin, cos) = true_anomaly.sin_cos();
+
+        let r = self.radius_at(true_anomaly);
+        let speed = (mu / self.semi_latus()).sqrt();
+
+        let position = DVec3::new(r * cos, r * sin, 0.0);
+        let velocity = DVec3::new(-speed * sin, speed * (self.eccentricity + cos), 0.0);
+
+        (self.to_reference(position), self.to_reference(velocity))
     }
 
     ///Position at time t (seconds) relative to parent, in meters.
     pub fn position_at_time(&self, t: f64, mass: f64, parent_mass: f64) -> (f64, f64) {
-        let a = self.semi_major_axis();
-        let e = self.eccentricity();
-        let b = a * (1.0 - e * e).sqrt();
+        let (position, _) = self.state_at_time(t, mass, parent_mass);
+        (position.x, position.y)
+    }
 
-        //get angular velocity
-        let n = self.mean_motion(mass, parent_mass);
-        let m_anom = n * t + self.offset;
+    ///Velocity at time t (seconds) relative to parent, in m/s.
+    pub fn velocity_at_time(&self, t: f64, mass: f64, parent_mass: f64) -> (f64, f64) {
+        let (_, velocity) = self.state_at_time(t, mass, parent_mass);
+        (velocity.x, velocity.y)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //parent mass that makes the gravitational parameter 1
+    const PARENT_MASS: f64 = 1.0 / G;
+
+    //state vectors to elements and back again at the epoch, then on along the orbit
+    fn assert_round_trip(position: DVec3, velocity: DVec3) {
+        let orbit = Orbit::from_state_vectors(position, velocity, 0.0, PARENT_MASS, 0.0);
+        let (p, v) = orbit.state_at_time(0.0, 0.0, PARENT_MASS);
+        assert!((p - position).length() < 1e-9 * position.length(), "{orbit:?}: position {p} vs {position}");
+        assert!((v - velocity).length() < 1e-9 * velocity.length(), "{orbit:?}: velocity {v} vs {velocity}");
+
+        //energy and angular momentum hold along the way
+        let energy = |p: DVec3, v: DVec3| v.length_squared() / 2.0 - 1.0 / p.length();
+        let (later, later_velocity) = orbit.state_at_time(3.0, 0.0, PARENT_MASS);
+        assert!((energy(later, later_velocity) - energy(position, velocity)).abs() < 1e-9);
+        assert!((later.cross(later_velocity) - position.cross(velocity)).length() < 1e-9);
+    }
+
+    #[test]
+    fn elliptical_state_vectors_round_trip() {
+        //eccentric, in the plane
+        assert_round_trip(DVec3::new(1.0, 0.5, 0.0), DVec3::new(-0.3, 0.9, 0.0));
+        //the same the other way round
+        assert_round_trip(DVec3::new(1.0, 0.5, 0.0), DVec3::new(0.3, -0.9, 0.0));
+        //tilted out of the plane
+        assert_round_trip(DVec3::new(1.0, 0.2, 0.3), DVec3::new(-0.1, 0.8, 0.4));
+        //circular, where there's no periapsis to measure from
+        assert_round_trip(DVec3::new(0.0, 2.0, 0.0), DVec3::new(-(0.5f64).sqrt(), 0.0, 0.0));
+    }
+
+    #[test]
+    fn elements_round_trip() {
+        let orbit = Orbit::from_elements(2.0, 0.3, 0.4, 1.0, 2.0, 0.5, 0.0);
+        let (position, velocity) = orbit.state_at_time(1.0, 0.0, PARENT_MASS);
+        let fitted = Orbit::from_state_vectors(position, velocity, 0.0, PARENT_MASS, 1.0);
 
-        //solve for eccentric anomaly
-        let e_anom = self.eccentric_anomaly(m_anom, e);
+        assert!((fitted.semi_major_axis() - 2.0).abs() < 1e-9);
+        assert!((fitted.eccentricity - 0.3).abs() < 1e-9);
+        assert!((fitted.inclination - 0.4).abs() < 1e-9);
+        assert!((fitted.ascending_node - 1.0).abs() < 1e-9);
+        assert!((fitted.argument_of_periapsis - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn kepler_converges_for_ellipses() {
+        for eccentricity in [0.0, 0.1, 0.5, 0.9, 0.99, 0.999] {
+            let orbit = Orbit::from_elements(1.0, eccentricity, 0.0, 0.0, 0.0, 0.0, 0.0);
+            for i in 0..64 {
+                let mean_anomaly = TAU * i as f64 / 64.0;
+                let solution = orbit.solve_kepler(mean_anomaly, KEPLER_TOLERANCE);
+                assert!(solution.converged, "e = {eccentricity}, M = {mean_anomaly}");
+                assert!(solution.residual < 1e-9, "e = {eccentricity}, M = {mean_anomaly}: {}", solution.residual);
 
-        //convert to (x, y) in orbital plane
-        let x = a * (e_anom.cos() - e);
-        let y = b * e_anom.sin();
+                let back = orbit.mean_from_eccentric(solution.anomaly).rem_euclid(TAU);
+                let wrapped = (back - mean_anomaly + PI).rem_euclid(TAU) - PI;
+                assert!(wrapped.abs() < 1e-9, "e = {eccentricity}, M = {mean_anomaly}: {back}");
+            }
+        }
+    }
 
-        (x, y)
+    #[test]
+    fn escape_state_vectors_round_trip() {
+        //hyperbolic, on the way in and on the way out
+        assert_round_trip(DVec3::new(1.0, 0.0, 0.0), DVec3::new(-0.5, 1.6, 0.0));
+        assert_round_trip(DVec3::new(1.0, 0.0, 0.0), DVec3::new(0.5, 1.6, 0.0));
+        //parabolic, at exactly escape speed
+        assert_round_trip(DVec3::new(0.0, 1.0, 0.0), DVec3::new(-(2.0f64).sqrt(), 0.0, 0.0));
+
+        //the excess speed is what the energy leaves
+        let orbit = Orbit::from_state_vectors(DVec3::X, DVec3::new(0.5, 1.6, 0.0), 0.0, PARENT_MASS, 0.0);
+        assert_eq!(orbit.conic(), Conic::Hyperbola);
+        let excess = orbit.excess_velocity(0.0, PARENT_MASS).unwrap();
+        assert!((excess.powi(2) - (0.5f64.powi(2) + 1.6f64.powi(2) - 2.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn kepler_converges_for_escape_trajectories() {
+        for eccentricity in [1.001, 1.1, 2.0, 10.0] {
+            let orbit = Orbit::from_elements(-1.0, eccentricity, 0.0, 0.0, 0.0, 0.0, 0.0);
+            for mean_anomaly in [-1000.0, -10.0, -0.5, 0.0, 1e-6, 0.5, 10.0, 1000.0] {
+                let solution = orbit.solve_kepler(mean_anomaly, KEPLER_TOLERANCE);
+                assert!(solution.converged, "e = {eccentricity}, M = {mean_anomaly}");
+
+                let back = orbit.mean_from_eccentric(solution.anomaly);
+                assert!((back - mean_anomaly).abs() < 1e-9 * mean_anomaly.abs().max(1.0), "e = {eccentricity}, M = {mean_anomaly}: {back}");
+            }
+        }
+
+        //barker's equation has a closed form
+        let parabola = Orbit { periapsis: 1.0, eccentricity: 1.0, ..default() };
+        for mean_anomaly in [-100.0, -1.0, 0.0, 1.0, 100.0] {
+            let solution = parabola.solve_kepler(mean_anomaly, KEPLER_TOLERANCE);
+            assert!(solution.residual < 1e-9 * mean_anomaly.abs().max(1.0), "M = {mean_anomaly}: {}", solution.residual);
+        }
     }
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 
-use crate::stellar_core::solar_system::Orbit;
+use crate::stellar_core::solar_system::{Atmosphere, Ephemeris, Orbit, Star, Luminosity, Mass};
+use crate::stellar_core::clock::SimulationClock;
+use crate::stellar_utils::unit_conversion::*;
 
 use crate::procedural_generation;
 
@@ -12,13 +14,33 @@ pub struct Planet {
     pub surface_gravity: f64,
     pub atmos_pressure: f64,
     pub surface_temperature: f64,
+    pub equilibrium_temperature: f64,
+    pub albedo: f64,
     pub atmosphere_composition: Vec<(String, f64)>,
     pub magnetic_field_strength: f64,
     pub tectonic_activity: String,
     pub habitability: f64,
+    ///Still accreting material from the circumstellar disk.
+    pub protoplanet: bool,
+    ///Mass in earth masses a protoplanet grows towards while its star's disk lasts. Once it stops growing
+    ///this is whatever mass it reached, which falls short if the disk ran out first.
+    pub final_mass: f64,
+    ///Surface a protoplanet settles into once it stops growing. Only protoplanets have one.
+    pub settled: Option<Surface>,
     pub orbit: Orbit,
 }
 
+///The generated atmosphere, climate and geology of a finished planet, kept aside while it's still molten.
+#[derive(Clone, Debug, Default)]
+pub struct Surface {
+    pub atmos_pressure: f64,
+    pub atmosphere_composition: Vec<(String, f64)>,
+    ///Greenhouse warming on top of the equilibrium temperature, in kelvin.
+    pub greenhouse: f64,
+    pub tectonic_activity: String,
+    pub habitability: f64,
+}
+
 impl Default for Planet {
     fn default() -> Self {
         Planet { 
@@ -28,10 +50,15 @@ impl Default for Planet {
             surface_gravity: 0.0, 
             atmos_pressure: 0.0, 
             surface_temperature: 0.0, 
+            equilibrium_temperature: 0.0,
+            albedo: 0.0,
             atmosphere_composition: vec![], 
             magnetic_field_strength: 0.0, 
             tectonic_activity: "".to_string(), 
             habitability: 0.0,
+            protoplanet: false,
+            final_mass: 0.0,
+            settled: None,
             orbit: Orbit::default()
         }
     }
@@ -51,14 +78,91 @@ impl std::fmt::Debug for Planet {
 }
 
 impl Planet {
+    ///Generates a planet of this mass (earth masses) and density (kg/m^3), receiving this much
+    ///flux relative to earth.
+    pub fn new(earth_mass: f64, density: f64, solar_flux: f64, magnetic_field: f64, orbit: Orbit) -> Self {
+        let data = procedural_generation::gen_planet::generate_planet(
+            earth_mass, density, solar_flux, magnetic_field, orbit.clone()
+        );
+
+        Planet {
+            mass: data.mass,
+            density: data.density,
+            radius: data.radius,
+            surface_gravity: data.surface_gravity,
+            atmos_pressure: data.atmos_pressure,
+            surface_temperature: data.surface_temperature,
+            equilibrium_temperature: data.equilibrium_temperature,
+            albedo: data.albedo,
+            atmosphere_composition: data.atmosphere_composition,
+            magnetic_field_strength: data.magnetic_field_strength,
+            tectonic_activity: data.tectonic_activity,
+            habitability: data.habitability,
+            protoplanet: false,
+            final_mass: data.mass,
+            settled: None,
+            orbit,
+        }
+    }
+
+    ///The planet's air, if it has any.
+    pub fn atmosphere(&self) -> Option<Atmosphere> {
+        let gas_giant = self.atmosphere_composition.iter().any(|(composition, _)| composition == "gas giant");
+        Atmosphere::new(self.atmos_pressure, self.surface_temperature, self.surface_gravity, gas_giant)
+    }
+
+    ///Turns this planet into a protoplanet that has gathered `progress` (0 to 1) of its final mass.
+    ///It has no atmosphere yet and its surface is still molten from the impacts, so the generated
+    ///surface is put aside until it settles.
+    pub fn into_protoplanet(mut self, progress: f64) -> Self {
+        self.settled = Some(Surface {
+            atmos_pressure: self.atmos_pressure,
+            atmosphere_composition: std::mem::take(&mut self.atmosphere_composition),
+            greenhouse: self.surface_temperature - self.equilibrium_temperature,
+            tectonic_activity: std::mem::take(&mut self.tectonic_activity),
+            habitability: self.habitability,
+        });
+
+        self.final_mass = self.mass;
+        self.mass *= progress.clamp(0.01, 1.0);
+        self.radius *= progress.clamp(0.01, 1.0).cbrt();
+        self.atmos_pressure = 0.0;
+        self.surface_temperature = self.surface_temperature.max(1500.0);
+        self.tectonic_activity = "Permanent Resurfacing".to_string();
+        self.habitability = 0.0;
+        self.protoplanet = true;
+
+        self
+    }
+
+    ///Stops a protoplanet growing at the mass it has reached. It cools down to the surface
+    ///that was generated for it, warmed by its greenhouse on top of the light it gets now.
+    pub fn settle(&mut self) {
+        self.protoplanet = false;
+        self.final_mass = self.mass;
+
+        let Some(surface) = self.settled.take() else { return };
+        self.atmos_pressure = surface.atmos_pressure;
+        self.atmosphere_composition = surface.atmosphere_composition;
+        self.surface_temperature = self.equilibrium_temperature + surface.greenhouse;
+        self.tectonic_activity = surface.tectonic_activity;
+        self.habitability = surface.habitability;
+    }
+
     pub fn get_bundle(
         planet: Self, x: f32, y: f32, images: &mut ResMut<Assets<Image>>
     ) -> (Self, Sprite, Transform) {
         let radius = planet.radius;
         let tex_size = (radius as u32 / 100).max(1);
 
+        //protoplanets glow from accretion heat
+        let (r, g, b) = match planet.protoplanet {
+            true => (255, 90, 30),
+            false => (255, 200, 0),
+        };
+
         let sprite = Sprite {
-            image: procedural_generation::gen_icon::circle_texture(tex_size, tex_size, images, 255, 200, 0, 255),
+            image: procedural_generation::gen_icon::circle_texture(tex_size, tex_size, images, r, g, b, 255),
             custom_size: Some(Vec2::splat(radius as f32)),
             ..default()
         };
@@ -69,4 +173,93 @@ impl Planet {
             Transform::from_xyz(x, y, 0.0).with_scale(Vec3::splat(0.05))
         )
     }
+
+    ///Recalculates equilibrium temperatures from the current luminosity of every star, at the distance
+    ///each star actually is, so moons and planets in binaries get the right light.
+    ///The greenhouse contribution on top of the equilibrium temperature is preserved.
+    pub fn update_temperature(
+        clock: Res<SimulationClock>,
+        ephemeris: Res<Ephemeris>,
+        stars: Query<(Entity, &Luminosity), With<Star>>,
+        mut planets: Query<(Entity, &mut Planet)>
+    ) {
+        let t = clock.time();
+
+        for (entity, mut planet) in planets.iter_mut() {
+            let Some(position) = ephemeris.position_at(entity, t) else { continue };
+
+            let equilibrium_temperature: f64 = stars.iter()
+                .filter_map(|(star, luminosity)| {
+                    let distance = ephemeris.position_at(star, t)?.distance(position);
+                    (distance > 0.0).then_some((luminosity, distance))
+                })
+                .map(|(luminosity, distance)| procedural_generation::gen_planet::equilibrium_temperature(
+                    **luminosity, distance, planet.albedo
+                ).powi(4))
+                .sum::<f64>()
+                .powf(0.25);
+
+            let greenhouse = planet.surface_temperature - planet.equilibrium_temperature;
+            planet.equilibrium_temperature = equilibrium_temperature;
+            planet.surface_temperature = equilibrium_temperature + greenhouse;
+        }
+    }
+
+    ///Protoplanets sweep up material from their own star's disk, growing towards their final mass
+    ///faster the more of the disk is left. Once the disk has dissipated they stop growing and settle into ordinary planets.
+    ///They grow at the clock's evolution scale, keeping pace with the disk.
+    pub fn update_protoplanets(
+        clock: Res<SimulationClock>,
+        ephemeris: Res<Ephemeris>,
+        stars: Query<&Star>,
+        mut planets: Query<(Entity, &mut Planet, &mut Mass)>
+    ) {
+        let dt_gy = to_gigayears(clock.evolution_delta_secs());
+
+        for (entity, mut planet, mut mass) in planets.iter_mut() {
+            if !planet.protoplanet {
+                continue;
+            }
+
+            //up the hierarchy to the star the planet goes round, past any barycentres
+            let mut body = entity;
+            let star = loop {
+                let Some(parent) = ephemeris.parent(body) else { break None };
+                if let Ok(star) = stars.get(parent) {
+                    break Some(star);
+                }
+                body = parent;
+            };
+
+            let Some((star, disk)) = star.and_then(|star| Some((star, star.disk.as_ref()?))) else {
+                planet.settle();
+                continue;
+            };
+
+            //logistic growth that e-folds every million years with a fresh disk, slowing as the disk thins
+            //and as the planet nears its final mass
+            let remaining = (disk.mass / (0.01 * star.mass)).clamp(0.0, 1.0);
+            let growth = (-remaining * dt_gy / 0.001).exp();
+            let grown = planet.final_mass / (1.0 + (planet.final_mass / planet.mass - 1.0) * growth);
+
+            let ratio = grown / planet.mass;
+            planet.mass = grown;
+            planet.radius *= ratio.cbrt();
+            **mass *= ratio;
+        }
+    }
+
+    ///Rings the top of each atmosphere, at the same scale as the planet's sprite.
+    pub fn draw_atmospheres(mut gizmos: Gizmos, planets: Query<(&Planet, &Sprite, &Transform)>) {
+        for (planet, sprite, transform) in planets.iter() {
+            let Some(atmosphere) = planet.atmosphere() else { continue };
+            let Some(size) = sprite.custom_size else { continue };
+
+            //planet radius is in km
+            let edge = 1.0 + atmosphere.height() / (planet.radius * 1000.0);
+            let radius = size.x * transform.scale.x / 2.0 * edge as f32;
+
+            gizmos.circle_2d(transform.translation.xy(), radius, Color::srgba(0.4, 0.7, 1.0, 0.4));
+        }
+    }
 }
\ No newline at end of file
@@ -90,6 +90,24 @@ impl<T> MTree<T> where T: std::fmt::Debug {
         Some(new_id)
     }
 
+    ///Returns the Id of the root node.
+    pub fn root_id(&self) -> Id {
+        self.first_id
+    }
+
+    ///Returns the Ids of the children of the node at this Id, in insertion order.
+    pub fn children(&self, node_id: Id) -> Vec<Id> {
+        match self.get_node(node_id) {
+            Some(node) => node.children.clone(),
+            None => vec![]
+        }
+    }
+
+    ///Returns the Id of the parent of the node at this Id, if it has one.
+    pub fn parent(&self, node_id: Id) -> Option<Id> {
+        self.get_node(node_id)?.parent
+    }
+
     ///Returns the value of a node at this ID.
     pub fn get_value(&self, node_id: Id) -> Option<&T> {
         match self.node_map.get(&node_id) {
@@ -98,6 +116,40 @@ impl<T> MTree<T> where T: std::fmt::Debug {
         }
     }
 
+    ///Returns a mutable reference to the value of a node at this ID.
+    pub fn get_value_mut(&mut self, node_id: Id) -> Option<&mut T> {
+        match self.node_map.get_mut(&node_id) {
+            Some((_, value)) => Some(value),
+            None => None
+        }
+    }
+
+    ///Removes the node at this ID along with everything below it, and returns its value.
+    ///The root cannot be removed.
+    pub fn remove(&mut self, node_id: Id) -> Result<T, NodeError> {
+        let parent = match self.get_node(node_id) {
+            Some(Node { parent: Some(parent), .. }) => *parent,
+            _ => return Err(NodeError::NotFound(node_id))
+        };
+
+        //detach from the parent first so the subtree is unreachable
+        if let Some(parent_node) = self.get_node_mut(parent) {
+            parent_node.children.retain(|&child| child != node_id);
+        }
+
+        //then drop every descendant
+        let mut stack = self.children(node_id);
+        while let Some(id) = stack.pop() {
+            stack.extend(self.children(id));
+            self.node_map.remove(&id);
+        }
+
+        match self.node_map.remove(&node_id) {
+            Some((_, value)) => Ok(value),
+            None => Err(NodeError::NotFound(node_id))
+        }
+    }
+
     //a: this function is private because we do not need to expose Node
     ///Returns just the node in the tree at this ID.
     fn get_node(&self, node_id: Id) -> Option<&Node> {
@@ -206,6 +258,11 @@ impl<'a, T> NodeHandle<'a, T> where T: std::fmt::Debug {
             for child in node.children.iter_mut() {
                 *child += id_difference;
             }
+            //and parents. the donor root gets this node as its new parent
+            node.parent = match node.parent {
+                Some(parent) => Some(parent + id_difference),
+                None => Some(self.id)
+            };
             //reinsert into destination tree nodemap
             self.tree.node_map.insert(id + id_difference, (node, data));
         }
@@ -1,5 +1,20 @@
 
 
+//physical constants, in SI units
+
+///Gravitational constant, in m³/(kg s²).
+pub const G: f64 = 6.6743015e-11;
+///Speed of light, in m/s.
+pub const SPEED_OF_LIGHT: f64 = 299_792_458.0;
+///Stefan-Boltzmann constant, in W/(m² K⁴).
+pub const STEFAN_BOLTZMANN: f64 = 5.670374419e-8;
+///Mass of the Sun, in kg.
+pub const SOL_MASS: f64 = 1.9885e30;
+///Radius of the Sun, in meters.
+pub const SOL_RADIUS: f64 = 6.957e8;
+///Luminosity of the Sun, in watts.
+pub const SOL_LUMINOSITY: f64 = 3.828e26;
+
 ///Returns the Moon mass in kilo times n.
 pub fn moons(n: f64) -> f64 {
     n * 7.346e22
@@ -17,7 +32,7 @@ pub fn jupiters(n: f64) -> f64 {
 
 ///Returns Sol mass in kilo times n.
 pub fn sols(n: f64) -> f64 {
-    n * 1.9885e30
+    n * SOL_MASS
 }
 
 ///Returns the mass in moon masses times n.
@@ -37,5 +52,57 @@ pub fn to_jupiter(n: f64) -> f64 {
 
 ///Returns mass in solar masses times n.
 pub fn to_solar(n: f64) -> f64 {
-    n / 1.9885e30
-}
\ No newline at end of file
+    n / SOL_MASS
+}
+
+///Returns the number of seconds in n julian years.
+pub fn years(n: f64) -> f64 {
+    n * 3.15576e7
+}
+
+///Returns the number of seconds in n billion years.
+pub fn gigayears(n: f64) -> f64 {
+    n * 3.15576e16
+}
+
+///Returns the time in julian years of n seconds.
+pub fn to_years(n: f64) -> f64 {
+    n / 3.15576e7
+}
+
+///Returns the time in billions of years of n seconds.
+pub fn to_gigayears(n: f64) -> f64 {
+    n / 3.15576e16
+}
+
+///Returns the distance in meters of n astronomical units.
+pub fn au(n: f64) -> f64 {
+    n * 1.495978707e11
+}
+
+///Returns the distance in astronomical units of n meters.
+pub fn to_au(n: f64) -> f64 {
+    n / 1.495978707e11
+}
+
+///Formats n seconds for the HUD as days and a clock, like 12D 03:04:05. Anything endless reads as a dash.
+pub fn format_duration(n: f64) -> String {
+    if !n.is_finite() {
+        return "-".to_string();
+    }
+
+    let seconds = n.max(0.0) as u64;
+    let (days, seconds) = (seconds / 86_400, seconds % 86_400);
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    format!("{days}D {hours:02}:{minutes:02}:{seconds:02}")
+}
+
+///Formats n meters for the HUD: km up close, AU further out. Anything endless reads as a dash.
+pub fn format_distance(n: f64) -> String {
+    match n {
+        n if !n.is_finite() => "-".to_string(),
+        n if n < 1e10 => format!("{:.0} km", n / 1000.0),
+        n => format!("{:.3} AU", to_au(n)),
+    }
+}
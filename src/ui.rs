@@ -1,4 +1,10 @@
 pub mod info_ui;
 mod fps_ui;
 mod cam_mode_ui;
-mod coords_ui;
\ No newline at end of file
+mod coords_ui;
+mod clock_ui;
+mod markers_ui;
+mod maneuver_ui;
+mod orbit_ui;
+mod lagrange_ui;
+mod planner_ui;
\ No newline at end of file
@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+use crate::stellar_core::clock::SimulationClock;
+
+pub struct ClockUIPlugin;
+impl Plugin for ClockUIPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, setup)
+            .add_systems(Update, update)
+        ;
+    }
+}
+
+//marker struct to identify the span
+#[derive(Component)]
+struct ClockUIMarker;
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+
+    let font = TextFont {
+        font: asset_server.load("fonts/vcr_osd_mono.ttf"),
+        font_size: 30.0,
+        ..default()
+    };
+
+    commands.spawn((
+        Text::new("MET (,./ P -=): "),
+        font.clone(),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(90.0),
+            left: Val::Px(5.0),
+            ..default()
+        },
+    ))
+    .with_child((
+        TextSpan::default(),
+        font,
+        ClockUIMarker
+    ))
+    ;
+
+}
+
+fn update(mut query: Query<&mut TextSpan, With<ClockUIMarker>>, clock: Res<SimulationClock>) {
+    //mission elapsed time as Y000 D000 00:00:00
+    let seconds = clock.elapsed as u64;
+    let (years, seconds) = (seconds / 31_557_600, seconds % 31_557_600);
+    let (days, seconds) = (seconds / 86_400, seconds % 86_400);
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    let rate = match clock.paused {
+        true => "PAUSED".to_string(),
+        false => format!("{}x", clock.warp),
+    };
+    //stars only age faster than everything else when asked to
+    let evolution = match clock.evolution_scale > 1.0 {
+        true => format!(" EVO {}x", clock.evolution_scale),
+        false => String::new(),
+    };
+
+    for mut span in &mut query {
+        **span = format!("Y{years} D{days:03} {hours:02}:{minutes:02}:{seconds:02} {rate}{evolution}");
+    }
+}
@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 use crate::stellar_core;
+use crate::stellar_core::floating_origin::WorldPosition;
+use crate::stellar_core::solar_system::to_world;
 
 pub struct CoordsUIPlugin;
 impl Plugin for CoordsUIPlugin {
@@ -45,11 +47,13 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
 
 fn update(
     mut query: Query<&mut TextSpan, With<CoordsUIMarker>>,
-    ship_query: Query<&mut Transform, (With<stellar_core::ship::Ship>, Without<Camera2d>)>
+    ship_query: Query<&WorldPosition, With<stellar_core::ship::Ship>>
 ) {
     for mut span in &mut query {
         for ship in &ship_query {
-            **span = format!("X{0}, Y{1}", ship.translation.x as i32, ship.translation.y as i32);
+            //world units, not meters, so the numbers stay readable
+            let position = to_world(**ship);
+            **span = format!("X{0}, Y{1}", position.x as i32, position.y as i32);
         }
     }
 }
@@ -9,6 +9,12 @@ impl Plugin for UIPlugin {
             .add_plugins(crate::ui::fps_ui::FPSUIPlugin)
             .add_plugins(crate::ui::cam_mode_ui::CamUIPlugin)
             .add_plugins(crate::ui::coords_ui::CoordsUIPlugin)
+            .add_plugins(crate::ui::clock_ui::ClockUIPlugin)
+            .add_plugins(crate::ui::markers_ui::MarkersUIPlugin)
+            .add_plugins(crate::ui::maneuver_ui::ManeuverUIPlugin)
+            .add_plugins(crate::ui::orbit_ui::OrbitUIPlugin)
+            .add_plugins(crate::ui::lagrange_ui::LagrangeUIPlugin)
+            .add_plugins(crate::ui::planner_ui::PlannerUIPlugin)
         ;
     }
 }
\ No newline at end of file
@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+use crate::stellar_core;
+use crate::stellar_core::solar_system::{Ephemeris, HoveredLagrange};
+use crate::stellar_core::clock::SimulationClock;
+use crate::stellar_utils::unit_conversion::to_au;
+
+//how far the tooltip sits from the cursor, in pixels
+const CURSOR_OFFSET: f32 = 16.0;
+
+pub struct LagrangeUIPlugin;
+impl Plugin for LagrangeUIPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, setup)
+            .add_systems(Update, update.after(stellar_core::solar_system::Lagrange::hover))
+        ;
+    }
+}
+
+//marker struct to identify the tooltip
+#[derive(Component)]
+struct LagrangeUIMarker;
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+
+    let font = TextFont {
+        font: asset_server.load("fonts/vcr_osd_mono.ttf"),
+        font_size: 16.0,
+        ..default()
+    };
+
+    commands.spawn((
+        Text::default(),
+        font,
+        TextLayout::new_with_justify(JustifyText::Left),
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        Visibility::Hidden,
+        LagrangeUIMarker
+    ));
+
+}
+
+//follows the cursor while it's over a Lagrange point
+fn update(
+    mut query: Query<(&mut Text, &mut Node, &mut Visibility), With<LagrangeUIMarker>>,
+    hovered: Res<HoveredLagrange>,
+    ephemeris: Res<Ephemeris>,
+    clock: Res<SimulationClock>,
+    ship: Query<&stellar_core::ship::Ship>,
+    q_windows: Query<&Window, With<bevy::window::PrimaryWindow>>
+) {
+    let Ok((mut text, mut node, mut visibility)) = query.get_single_mut() else { return };
+
+    let lagrange = hovered.0.and_then(|entity| {
+        let (_, lagrange) = ephemeris.lagrange_points().find(|(point, _)| *point == entity)?;
+        Some((entity, *lagrange))
+    });
+    let cursor = q_windows.get_single().ok().and_then(|window| window.cursor_position());
+
+    let (Some((entity, lagrange)), Some(cursor)) = (lagrange, cursor) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    let distance = match (ship.get_single(), ephemeris.position_at(entity, clock.time())) {
+        (Ok(ship), Some(position)) => format!("{:.3} AU away", to_au(ship.position.distance(position))),
+        _ => String::new(),
+    };
+
+    **text = format!(
+        "{} of {} and {}\n{distance}\nT to target",
+        lagrange.point.label(), ephemeris.name(lagrange.primary), ephemeris.name(lagrange.secondary)
+    );
+    node.left = Val::Px(cursor.x + CURSOR_OFFSET);
+    node.top = Val::Px(cursor.y + CURSOR_OFFSET);
+    *visibility = Visibility::Visible;
+}
@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+use crate::stellar_core;
+use crate::stellar_core::ship::maneuver::SelectedNode;
+use crate::stellar_utils::unit_conversion::format_duration;
+
+pub struct ManeuverUIPlugin;
+impl Plugin for ManeuverUIPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, setup)
+            .add_systems(Update, update)
+        ;
+    }
+}
+
+//marker struct to identify the span
+#[derive(Component)]
+struct ManeuverUIMarker;
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+
+    let font = TextFont {
+        font: asset_server.load("fonts/vcr_osd_mono.ttf"),
+        font_size: 20.0,
+        ..default()
+    };
+
+    commands.spawn((
+        Text::new("Node (N Tab WS DA XZ Del): "),
+        font.clone(),
+        TextLayout::new_with_justify(JustifyText::Left),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        },
+    ))
+    .with_child((
+        TextSpan::default(),
+        font,
+        ManeuverUIMarker
+    ))
+    ;
+
+}
+
+fn update(
+    mut query: Query<&mut TextSpan, With<ManeuverUIMarker>>,
+    selected: Res<SelectedNode>,
+    ship: Query<&stellar_core::ship::Ship>
+) {
+    let Ok(ship) = ship.get_single() else { return };
+
+    //the burn in progress, otherwise the selected node, otherwise the next one
+    let text = match (ship.burn, selected.0.or(Some(0)).and_then(|i| ship.maneuvers.get(i))) {
+        (Some(remaining), _) => format!("BURNING {:.1} m/s left", remaining.length()),
+        (None, Some(node)) => format!(
+            "\nT-{} dv {:.1} m/s burn {:.0} s\nP {:+.1} R {:+.1}",
+            format_duration(node.burn_start() - ship.time), node.delta_v(), node.burn_time(),
+            node.prograde, node.radial
+        ),
+        (None, None) => "none".to_string(),
+    };
+
+    for mut span in &mut query {
+        **span = text.clone();
+    }
+}
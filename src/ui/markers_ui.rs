@@ -0,0 +1,73 @@
+use bevy::prelude::*;
+use crate::stellar_core;
+use crate::stellar_core::navigation::MarkerKind;
+use crate::stellar_utils::unit_conversion::{format_distance, format_duration};
+
+//upcoming events listed at once
+const MAX_LISTED: usize = 5;
+
+pub struct MarkersUIPlugin;
+impl Plugin for MarkersUIPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, setup)
+            .add_systems(Update, update)
+        ;
+    }
+}
+
+//marker struct to identify the span
+#[derive(Component)]
+struct MarkersUIMarker;
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+
+    let font = TextFont {
+        font: asset_server.load("fonts/vcr_osd_mono.ttf"),
+        font_size: 20.0,
+        ..default()
+    };
+
+    commands.spawn((
+        Text::new("Nav (T):"),
+        font.clone(),
+        TextLayout::new_with_justify(JustifyText::Left),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            right: Val::Px(5.0),
+            ..default()
+        },
+    ))
+    .with_child((
+        TextSpan::default(),
+        font,
+        MarkersUIMarker
+    ))
+    ;
+
+}
+
+fn update(mut query: Query<&mut TextSpan, With<MarkersUIMarker>>, ship: Query<&stellar_core::ship::Ship>) {
+    let Ok(ship) = ship.get_single() else { return };
+
+    //impacts say how hard the ship would come down
+    let label = |marker: &stellar_core::navigation::Marker| match (marker.kind, ship.prediction.impact) {
+        (MarkerKind::Impact, Some(contact)) => format!("{} {:.0} m/s", contact.kind.label(), contact.speed),
+        (kind, _) => kind.label().to_string(),
+    };
+
+    //one line per event as PE T-0D 00:00:00 1234 km
+    let lines: String = ship.prediction.markers.iter()
+        .filter(|marker| marker.time > ship.time)
+        .take(MAX_LISTED)
+        .map(|marker| format!(
+            "\n{} T-{} {}",
+            label(marker), format_duration(marker.time_to(ship.time)), format_distance(marker.distance)
+        ))
+        .collect();
+
+    for mut span in &mut query {
+        **span = lines.clone();
+    }
+}
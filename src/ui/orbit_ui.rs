@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+use crate::stellar_core;
+use crate::stellar_core::navigation::ContactKind;
+use crate::stellar_core::solar_system::Ephemeris;
+use crate::stellar_core::ship::autopilot::{Autopilot, AutopilotProgress};
+use crate::stellar_utils::unit_conversion::{format_distance, format_duration};
+
+pub struct OrbitUIPlugin;
+impl Plugin for OrbitUIPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, setup)
+            .add_systems(Update, update)
+        ;
+    }
+}
+
+//marker struct to identify the span
+#[derive(Component)]
+struct OrbitUIMarker;
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+
+    let font = TextFont {
+        font: asset_server.load("fonts/vcr_osd_mono.ttf"),
+        font_size: 20.0,
+        ..default()
+    };
+
+    commands.spawn((
+        Text::new("Orbit:"),
+        font.clone(),
+        TextLayout::new_with_justify(JustifyText::Left),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            right: Val::Px(5.0),
+            ..default()
+        },
+    ))
+    .with_child((
+        TextSpan::default(),
+        font,
+        OrbitUIMarker
+    ))
+    ;
+
+}
+
+fn update(
+    mut query: Query<&mut TextSpan, With<OrbitUIMarker>>,
+    ephemeris: Res<Ephemeris>,
+    autopilot: Res<Autopilot>,
+    mut progress: EventReader<AutopilotProgress>,
+    mut latest: Local<Option<AutopilotProgress>>,
+    ship: Query<&stellar_core::ship::Ship>
+) {
+    if let Some(event) = progress.read().last() {
+        *latest = Some(*event);
+    }
+    if autopilot.command.is_none() {
+        *latest = None;
+    }
+
+    let Ok(ship) = ship.get_single() else { return };
+
+    let text = match (&ship.osculating, ship.contact) {
+        (_, Some(contact)) => match contact.kind {
+            ContactKind::Crash => " CRASHED".to_string(),
+            _ => " LANDED".to_string(),
+        },
+        (Some(patch), None) => {
+            let orbit = &patch.orbit;
+            let parent_mass = ephemeris.mass(patch.reference);
+            let optional = |seconds: Option<f64>| seconds.map_or("-".to_string(), format_duration);
+
+            //escape trajectories have no apoapsis or period
+            let (apoapsis, period) = match orbit.is_bound() {
+                true => (format_distance(orbit.apoapsis()), format_duration(orbit.period(0.0, parent_mass))),
+                false => ("ESCAPE".to_string(), "ESCAPE".to_string()),
+            };
+
+            format!(
+                "\nAP {apoapsis}\nPE {}\nECC {:.4}\nINC {:.1} DEG\nPER {period}\nT-AP {}\nT-PE {}",
+                format_distance(orbit.periapsis), orbit.eccentricity,
+                orbit.inclination.to_degrees(),
+                optional(orbit.time_to_apoapsis(ship.time, 0.0, parent_mass)),
+                optional(orbit.time_to_periapsis(ship.time, 0.0, parent_mass)),
+            )
+        },
+        (None, None) => " -".to_string(),
+    };
+
+    //the heat shield, flagged once it's past what it's rated for
+    let overheating = match ship.skin_temperature > ship.airframe.max_temperature {
+        true => " OVERHEAT",
+        false => "",
+    };
+    let sail = ship.airframe.sail.map_or("STOWED".to_string(), |sail| format!("{:+.0} DEG", sail.angle.to_degrees()));
+    let auto = match *latest {
+        Some(event) => format!(
+            "{} {:.0}%{}", event.command.label(), event.progress * 100.0,
+            event.countdown.map_or(String::new(), |seconds| format!(" IN {}", format_duration(seconds)))
+        ),
+        None => "OFF".to_string(),
+    };
+    let text = format!(
+        "{text}\nSKIN {:.0} K{overheating}\nSAIL (L [ ]) {sail}\nAUTO (V F Y M R B G Bksp) {auto}",
+        ship.skin_temperature
+    );
+
+    for mut span in &mut query {
+        **span = text.clone();
+    }
+}
@@ -0,0 +1,141 @@
+use bevy::prelude::*;
+use crate::stellar_core::clock::SimulationClock;
+use crate::stellar_core::navigation::Flyby;
+use crate::stellar_core::ship::planner::TransferPlanner;
+use crate::stellar_core::solar_system::Ephemeris;
+use crate::stellar_utils::unit_conversion::format_duration;
+
+//side of the porkchop plot, in pixels
+const PLOT_SIZE: f32 = 192.0;
+
+pub struct PlannerUIPlugin;
+impl Plugin for PlannerUIPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, setup)
+            .add_systems(Update, update)
+        ;
+    }
+}
+
+//marker structs to identify the panel and what's in it
+#[derive(Component)]
+struct PlannerUIMarker;
+
+#[derive(Component)]
+struct PlannerTextMarker;
+
+#[derive(Component)]
+struct PorkchopMarker;
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+
+    let font = TextFont {
+        font: asset_server.load("fonts/vcr_osd_mono.ttf"),
+        font_size: 16.0,
+        ..default()
+    };
+
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Percent(35.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(5.0),
+            ..default()
+        },
+        Visibility::Hidden,
+        PlannerUIMarker
+    ))
+    .with_children(|panel| {
+        panel.spawn((
+            Text::default(),
+            font,
+            TextLayout::new_with_justify(JustifyText::Left),
+            PlannerTextMarker
+        ));
+        panel.spawn((
+            ImageNode::default(),
+            Node {
+                width: Val::Px(PLOT_SIZE),
+                height: Val::Px(PLOT_SIZE),
+                ..default()
+            },
+            PorkchopMarker
+        ));
+    });
+
+}
+
+fn update(
+    planner: Res<TransferPlanner>,
+    clock: Res<SimulationClock>,
+    ephemeris: Res<Ephemeris>,
+    mut panel: Query<&mut Visibility, With<PlannerUIMarker>>,
+    mut text: Query<&mut Text, With<PlannerTextMarker>>,
+    mut plot: Query<(&mut ImageNode, &mut Node), With<PorkchopMarker>>
+) {
+    let Ok(mut visibility) = panel.get_single_mut() else { return };
+    *visibility = match planner.open {
+        true => Visibility::Visible,
+        false => Visibility::Hidden,
+    };
+    if !planner.open {
+        return;
+    }
+    let now = clock.time();
+
+    let estimates = match &planner.plan {
+        Some(plan) => format!(
+            "HOHMANN {:.0} m/s IN {}\nBI-ELLIPTIC {:.0} m/s IN {}\nPHASE {:.1} DEG, NEED {:.1} DEG\nWINDOW {}",
+            plan.hohmann.delta_v(), format_duration(plan.hohmann.flight_time),
+            plan.bi_elliptic.delta_v(), format_duration(plan.bi_elliptic.flight_time),
+            plan.current_phase.to_degrees(), plan.phase.to_degrees(),
+            plan.window.map_or("NEVER".to_string(), format_duration),
+        ),
+        None => "target a body that goes round the same one as you".to_string(),
+    };
+    //departures are counted from the start of the grid, which is when it was worked out
+    let best = planner.grid.as_ref().and_then(|grid| Some((grid.departures[0], grid.best()?)));
+    let best = match best {
+        Some((start, (departure, flight_time, delta_v))) => format!(
+            "\nLAMBERT {delta_v:.0} m/s, LEAVE IN {}, {} FLIGHT",
+            format_duration(departure - start), format_duration(flight_time)
+        ),
+        None => String::new(),
+    };
+
+    let flybys = match planner.searching() {
+        true => "\nFLYBYS SEARCHING...".to_string(),
+        false => planner.trajectories.iter().take(3).enumerate()
+            .map(|(i, trajectory)| format!(
+                "\n{} VIA {}: {:.0} m/s, LEAVE IN {}, {} FLIGHT",
+                i + 1, route(&ephemeris, &trajectory.flybys), trajectory.delta_v(),
+                format_duration((trajectory.departure - now).max(0.0)), format_duration(trajectory.arrival - trajectory.departure)
+            ))
+            .collect(),
+    };
+
+    if let Ok(mut text) = text.get_single_mut() {
+        **text = format!("Transfer (H J K 1-3):\n{estimates}{best}{flybys}");
+    }
+
+    if let Ok((mut image, mut node)) = plot.get_single_mut() {
+        match &planner.image {
+            Some(handle) => {
+                image.image = handle.clone();
+                node.display = Display::Flex;
+            },
+            None => node.display = Display::None,
+        }
+    }
+}
+
+//the bodies flown past, or straight there
+fn route(ephemeris: &Ephemeris, flybys: &[Flyby]) -> String {
+    match flybys.is_empty() {
+        true => "NONE".to_string(),
+        false => flybys.iter().map(|flyby| ephemeris.name(flyby.body)).collect::<Vec<_>>().join(" > "),
+    }
+}